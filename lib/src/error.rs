@@ -45,6 +45,11 @@ error_chain! {
             display("Cannot get a specific reference from repository")
         }
 
+        CannotReadConfigFile(path: String) {
+            description("Cannot read a file from the repository-level configuration")
+            display("Cannot read configuration file '{}'", path)
+        }
+
         CannotBuildTree {
             description("Cannot build Tree")
             display("Cannot build Tree")
@@ -80,6 +85,61 @@ error_chain! {
             display("Malformed trailer: {}", trailer)
         }
 
+        MalformedSchema(item: String) {
+            description("Found malformed metadata schema")
+            display("Malformed metadata schema: {}", item)
+        }
+
+        MalformedQuery(query: String) {
+            description("Found malformed search query")
+            display("Malformed search query: {}", query)
+        }
+
+        CannotReadIndex(path: String) {
+            description("Cannot read the full-text index")
+            display("Cannot read full-text index '{}'", path)
+        }
+
+        CannotWriteIndex(path: String) {
+            description("Cannot write the full-text index")
+            display("Cannot write full-text index '{}'", path)
+        }
+
+        MalformedIndex(line: String) {
+            description("Found malformed full-text index")
+            display("Malformed full-text index entry: {}", line)
+        }
+
+        UnknownPeriod(name: String) {
+            description("Unknown period")
+            display("Unknown period: {}", name)
+        }
+
+        CannotGetRefspecs(remote: String) {
+            description("Cannot get refspecs of remote")
+            display("Cannot get refspecs of remote {}", remote)
+        }
+
+        MalformedConfig(name: String) {
+            description("Found malformed configuration entry")
+            display("Malformed configuration entry: {}", name)
+        }
+
+        UnknownTrailerKey(key: String) {
+            description("Found trailer with unknown key")
+            display("Unknown metadata key: {}", key)
+        }
+
+        MisspelledTrailerKey(key: String, expected: String) {
+            description("Found trailer with misspelled key")
+            display("Unknown metadata key: {}, did you mean {}?", key, expected)
+        }
+
+        InvalidTrailerValue(key: String, value: String) {
+            description("Found trailer with invalid value")
+            display("Invalid value for {}: {}", key, value)
+        }
+
         EmptyMessage {
             description("An empty message was supplied")
             display("The message is empty")
@@ -0,0 +1,549 @@
+// git-dit - the distributed issue tracker for git
+// Copyright (C) 2017 Matthias Beyer <mail@beyermatthias.de>
+// Copyright (C) 2017 Julian Ganz <neither@nut.email>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Inverted full-text index
+//!
+//! On large trackers, scanning every message for each search is slow. This
+//! module provides the `Index` type, which maps the words occurring in messages
+//! to the messages containing them, along with their positions. An index may be
+//! stored on disk and updated incrementally: only messages not reachable from
+//! the tips recorded during the last update are processed.
+//!
+//! Like full-text queries, the index considers the subject, the lines of text
+//! in the body and the values of the trailers of a message. Words are maximal
+//! sequences of alphanumeric characters and are indexed in lower case.
+//!
+//! Indexes are queried via `IndexQuery`s, which consist of terms, phrases in
+//! double quotes and prefixes ending with `*`, e.g. `crash "on startup" conf*`.
+//! A message matches a query if it matches all of its parts. Matches are ranked
+//! by the frequency of the parts within the message, weighted by their
+//! inverse frequency among all messages.
+//!
+
+use git2::{self, Oid};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::result::Result as RResult;
+use std::str::FromStr;
+
+use error::*;
+use error::ErrorKind as EK;
+use issue::IssueRefType;
+use message::{LineIteratorExt, Message};
+use message::line::Line;
+use repository::RepositoryExt;
+
+
+/// First line of an index stored on disk, identifying the format
+///
+const FORMAT_HEADER: &'static str = "dit-index 1";
+
+
+/// Split a piece of text into words
+///
+/// The words returned are converted to lower case.
+///
+pub fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+
+/// Part of an index query
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryPart {
+    /// A single word
+    Term(String),
+    /// A sequence of words occurring in this exact order
+    Phrase(Vec<String>),
+    /// Any word starting with the prefix
+    Prefix(String),
+}
+
+impl QueryPart {
+    /// Create a part from a sequence of words
+    ///
+    fn from_words(mut words: Vec<String>) -> Option<Self> {
+        match words.len() {
+            0 => None,
+            1 => words.pop().map(QueryPart::Term),
+            _ => Some(QueryPart::Phrase(words)),
+        }
+    }
+}
+
+
+/// Query for an `Index`
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexQuery(pub Vec<QueryPart>);
+
+impl FromStr for IndexQuery {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+
+        // quoted phrases are found at odd positions
+        for (n, chunk) in s.split('"').enumerate() {
+            if n % 2 == 1 {
+                parts.extend(QueryPart::from_words(words(chunk)));
+                continue;
+            }
+
+            for token in chunk.split_whitespace() {
+                if token.ends_with('*') {
+                    let mut words = words(token.trim_end_matches('*'));
+                    let prefix = words.pop();
+                    parts.extend(words.into_iter().map(QueryPart::Term));
+                    parts.extend(prefix.map(QueryPart::Prefix));
+                } else {
+                    parts.extend(QueryPart::from_words(words(token)));
+                }
+            }
+        }
+
+        if parts.is_empty() {
+            return Err(Error::from_kind(EK::MalformedQuery(s.to_owned())));
+        }
+        Ok(IndexQuery(parts))
+    }
+}
+
+
+/// Message known to an index
+///
+#[derive(Clone, Debug, PartialEq)]
+struct IndexedMessage {
+    issue: Oid,
+    length: usize,
+}
+
+
+/// Message matching an index query
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hit {
+    pub message: Oid,
+    pub issue: Oid,
+    pub score: f64,
+}
+
+
+/// Inverted full-text index
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Index {
+    /// Message ids referred to by the refs of each issue at the last update
+    tips: BTreeMap<Oid, BTreeSet<Oid>>,
+    /// Messages indexed
+    messages: BTreeMap<Oid, IndexedMessage>,
+    /// Positions of each word in the messages containing it
+    postings: BTreeMap<String, BTreeMap<Oid, Vec<usize>>>,
+}
+
+impl Index {
+    /// Load an index from a file
+    ///
+    /// If the file doesn't exist, an empty index is returned.
+    ///
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Index::default()),
+            Err(err) => return Err(err).chain_err(|| EK::CannotReadIndex(path.to_string_lossy().into_owned())),
+        };
+
+        let mut lines = BufReader::new(file).lines();
+        let header = lines
+            .next()
+            .unwrap_or_else(|| Ok(String::new()))
+            .chain_err(|| EK::CannotReadIndex(path.to_string_lossy().into_owned()))?;
+        if header != FORMAT_HEADER {
+            return Err(Error::from_kind(EK::MalformedIndex(header)));
+        }
+
+        let mut index = Index::default();
+        for line in lines {
+            let line = line.chain_err(|| EK::CannotReadIndex(path.to_string_lossy().into_owned()))?;
+            index.read_line(&line)?;
+        }
+        Ok(index)
+    }
+
+    /// Read a single line of an index file into the index
+    ///
+    fn read_line(&mut self, line: &str) -> Result<()> {
+        let malformed = || Error::from_kind(EK::MalformedIndex(line.to_owned()));
+        let mut fields = line.split_whitespace();
+        let mut field = || fields.next().ok_or_else(&malformed);
+        let oid = |field: &str| Oid::from_str(field).chain_err(&malformed);
+
+        match field()? {
+            "tip" => {
+                let issue = oid(field()?)?;
+                let tip = oid(field()?)?;
+                self.tips.entry(issue).or_insert_with(BTreeSet::new).insert(tip);
+            },
+            "message" => {
+                let id = oid(field()?)?;
+                let issue = oid(field()?)?;
+                let length = field()?.parse().chain_err(&malformed)?;
+                self.messages.insert(id, IndexedMessage { issue: issue, length: length });
+            },
+            "word" => {
+                let word = field()?.to_owned();
+                let id = oid(field()?)?;
+                let positions = field()?
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<RResult<Vec<usize>, _>>()
+                    .chain_err(&malformed)?;
+                self.postings.entry(word).or_insert_with(BTreeMap::new).insert(id, positions);
+            },
+            _ => return Err(malformed()),
+        }
+        Ok(())
+    }
+
+    /// Store the index in a file
+    ///
+    /// The index is written to a temporary file first, which then replaces the
+    /// file at the path supplied.
+    ///
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp_path = path.with_extension("new");
+        let err = || EK::CannotWriteIndex(path.to_string_lossy().into_owned());
+
+        {
+            let mut file = io::BufWriter::new(File::create(&tmp_path).chain_err(&err)?);
+            writeln!(file, "{}", FORMAT_HEADER).chain_err(&err)?;
+            for (issue, tips) in self.tips.iter() {
+                for tip in tips {
+                    writeln!(file, "tip {} {}", issue, tip).chain_err(&err)?;
+                }
+            }
+            for (id, message) in self.messages.iter() {
+                writeln!(file, "message {} {} {}", id, message.issue, message.length).chain_err(&err)?;
+            }
+            for (word, occurrences) in self.postings.iter() {
+                for (id, positions) in occurrences {
+                    let positions : Vec<String> = positions.iter().map(ToString::to_string).collect();
+                    writeln!(file, "word {} {} {}", word, id, positions.join(",")).chain_err(&err)?;
+                }
+            }
+            file.flush().chain_err(&err)?;
+        }
+
+        fs::rename(tmp_path, path).chain_err(&err)
+    }
+
+    /// Get the number of messages indexed
+    ///
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Check whether the index is empty
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Get the message ids referred to by the refs of each issue at the last
+    /// update
+    ///
+    pub fn tips(&self) -> &BTreeMap<Oid, BTreeSet<Oid>> {
+        &self.tips
+    }
+
+    /// Add a message to the index
+    ///
+    /// The message is supplied as a sequence of lines, the first one being the
+    /// subject. If the message is already indexed, this function does nothing.
+    ///
+    pub fn add_message<I, S>(&mut self, id: Oid, issue: Oid, lines: I)
+        where I: IntoIterator<Item = S>,
+              S: AsRef<str>
+    {
+        if self.messages.contains_key(&id) {
+            return;
+        }
+
+        let mut lines = lines.into_iter();
+        let mut text : Vec<String> = lines.next().into_iter().map(|s| s.as_ref().to_owned()).collect();
+        for line in lines.categorized_lines() {
+            match line {
+                Line::Text(line) => text.push(line),
+                Line::Trailer(trailer) => text.push(trailer.value.to_string()),
+                Line::Blank => {},
+            }
+        }
+
+        let words : Vec<String> = text.iter().flat_map(|line| words(line)).collect();
+        self.messages.insert(id, IndexedMessage { issue: issue, length: words.len() });
+        for (position, word) in words.into_iter().enumerate() {
+            self.postings
+                .entry(word)
+                .or_insert_with(BTreeMap::new)
+                .entry(id)
+                .or_insert_with(Vec::new)
+                .push(position);
+        }
+    }
+
+    /// Update the index from a repository
+    ///
+    /// All messages of all issues which are not reachable from the tips
+    /// recorded during the last update are added to the index. The number of
+    /// messages added is returned.
+    ///
+    pub fn update(&mut self, repo: &git2::Repository) -> Result<usize> {
+        let mut count = 0;
+        for issue in repo.issues()? {
+            let issue = issue?;
+
+            // Messages referred to by refs created while walking will be
+            // considered during the next update.
+            let tips : BTreeSet<Oid> = issue
+                .all_refs(IssueRefType::Any)?
+                .filter_map(|reference| reference.ok().and_then(|r| r.target()))
+                .collect();
+
+            let mut revwalk = issue.message_revwalk()?;
+            for tip in self.tips.get(&issue.id()).into_iter().flat_map(|tips| tips) {
+                // tips may have vanished in the meantime
+                let _ = revwalk.hide(*tip);
+            }
+
+            for id in revwalk {
+                let id = id.chain_err(|| EK::CannotGetCommit)?;
+                if self.messages.contains_key(&id) {
+                    continue;
+                }
+
+                let message = repo.find_commit(id).chain_err(|| EK::CannotGetCommit)?;
+                self.add_message(id, issue.id(), message.message_lines());
+                count += 1;
+            }
+
+            self.tips.insert(issue.id(), tips);
+        }
+        Ok(count)
+    }
+
+    /// Get the number of occurrences of a query part in each message
+    ///
+    fn occurrences(&self, part: &QueryPart) -> BTreeMap<Oid, usize> {
+        match *part {
+            QueryPart::Term(ref term) => self.postings
+                .get(term)
+                .map(|occurrences| occurrences.iter().map(|(id, p)| (*id, p.len())).collect())
+                .unwrap_or_default(),
+            QueryPart::Prefix(ref prefix) => {
+                let mut retval = BTreeMap::new();
+                let words = self.postings
+                    .range(prefix.clone()..)
+                    .take_while(|&(word, _)| word.starts_with(prefix.as_str()));
+                for (_, occurrences) in words {
+                    for (id, positions) in occurrences {
+                        *retval.entry(*id).or_insert(0) += positions.len();
+                    }
+                }
+                retval
+            },
+            QueryPart::Phrase(ref words) => {
+                let postings : Vec<&BTreeMap<Oid, Vec<usize>>> = match words
+                    .iter()
+                    .map(|word| self.postings.get(word))
+                    .collect() {
+                    Some(postings) => postings,
+                    None => return BTreeMap::new(),
+                };
+
+                // count the positions at which the whole phrase starts
+                postings[0]
+                    .iter()
+                    .map(|(id, starts)| {
+                        let count = starts
+                            .iter()
+                            .filter(|start| postings[1..]
+                                .iter()
+                                .enumerate()
+                                .all(|(n, p)| p
+                                    .get(id)
+                                    .map(|positions| positions.binary_search(&(*start + n + 1)).is_ok())
+                                    .unwrap_or(false)
+                                )
+                            )
+                            .count();
+                        (*id, count)
+                    })
+                    .filter(|&(_, count)| count > 0)
+                    .collect()
+            },
+        }
+    }
+
+    /// Search the index
+    ///
+    /// Returns the messages matching the query, the best match first.
+    ///
+    pub fn search(&self, query: &IndexQuery) -> Vec<Hit> {
+        let total = self.messages.len() as f64;
+        let mut scores : Option<BTreeMap<Oid, f64>> = None;
+
+        for part in query.0.iter() {
+            let occurrences = self.occurrences(part);
+            let idf = (1.0 + total / (occurrences.len().max(1) as f64)).ln();
+
+            // only messages matching all parts are retained
+            let part_scores = occurrences
+                .into_iter()
+                .filter(|&(ref id, _)| scores.as_ref().map(|s| s.contains_key(id)).unwrap_or(true))
+                .filter_map(|(id, count)| self.messages.get(&id).map(|message| {
+                    let tf = count as f64 / (message.length.max(1) as f64);
+                    (id, tf * idf + scores.as_ref().and_then(|s| s.get(&id)).cloned().unwrap_or(0.0))
+                }))
+                .collect();
+            scores = Some(part_scores);
+        }
+
+        let mut hits : Vec<Hit> = scores
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(id, score)| self.messages.get(&id).map(|message| Hit {
+                message: id,
+                issue: message.issue,
+                score: score,
+            }))
+            .collect();
+        hits.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+        hits
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::TestingRepo;
+
+    fn test_index() -> Index {
+        let issue = Oid::from_str("1234567890123456789012345678901234567890").unwrap();
+        let mut index = Index::default();
+        index.add_message(
+            Oid::from_str("0000000000000000000000000000000000000001").unwrap(),
+            issue,
+            vec!["Crash on startup", "", "The program crashes on startup.", "", "Dit-status: open"],
+        );
+        index.add_message(
+            Oid::from_str("0000000000000000000000000000000000000002").unwrap(),
+            issue,
+            vec!["Re: Crash on startup", "", "Startup crash confirmed, crash crash crash."],
+        );
+        index
+    }
+
+    fn hit_ids(index: &Index, query: &str) -> Vec<String> {
+        index
+            .search(&query.parse().expect("Could not parse query"))
+            .into_iter()
+            .map(|hit| hit.message.to_string()[39..].to_owned())
+            .collect()
+    }
+
+    // Query tests
+
+    #[test]
+    fn parse_query() {
+        let query : IndexQuery = "Crash \"on startup\" conf* foo-bar".parse().expect("Could not parse query");
+        assert_eq!(query.0, vec![
+            QueryPart::Term(String::from("crash")),
+            QueryPart::Phrase(vec![String::from("on"), String::from("startup")]),
+            QueryPart::Prefix(String::from("conf")),
+            QueryPart::Phrase(vec![String::from("foo"), String::from("bar")]),
+        ]);
+
+        assert!(IndexQuery::from_str(" \"\" ").is_err());
+    }
+
+    // Index tests
+
+    #[test]
+    fn search() {
+        let index = test_index();
+        assert_eq!(hit_ids(&index, "crash"), vec!["2", "1"]);
+        assert_eq!(hit_ids(&index, "program"), vec!["1"]);
+        assert_eq!(hit_ids(&index, "open"), vec!["1"]);
+        assert_eq!(hit_ids(&index, "\"startup crash\""), vec!["2"]);
+        assert_eq!(hit_ids(&index, "\"crash on\""), vec!["1", "2"]);
+        assert_eq!(hit_ids(&index, "conf*"), vec!["2"]);
+        assert_eq!(hit_ids(&index, "crash conf*"), vec!["2"]);
+        assert!(hit_ids(&index, "dit").is_empty());
+        assert!(hit_ids(&index, "\"startup on\"").is_empty());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut testing_repo = TestingRepo::new("index_save_and_load");
+        let path = testing_repo.repo().path().join("index");
+
+        assert!(Index::load(&path).expect("Could not load index").is_empty());
+
+        let index = test_index();
+        index.save(&path).expect("Could not save index");
+        assert_eq!(Index::load(&path).expect("Could not load index"), index);
+    }
+
+    #[test]
+    fn update() {
+        let mut testing_repo = TestingRepo::new("index_update");
+        let repo = testing_repo.repo();
+
+        let sig = git2::Signature::now("Foo Bar", "foo.bar@example.com")
+            .expect("Could not create signature");
+        let empty_tree = repo
+            .empty_tree()
+            .expect("Could not create empty tree");
+
+        let mut index = Index::default();
+        let issue = repo
+            .create_issue(&sig, &sig, "Test message 1", &empty_tree, vec![])
+            .expect("Could not create issue");
+        assert_eq!(index.update(repo).expect("Could not update index"), 1);
+        assert_eq!(index.update(repo).expect("Could not update index"), 0);
+
+        let initial_message = issue
+            .initial_message()
+            .expect("Could not retrieve initial message");
+        let message = issue
+            .add_message(&sig, &sig, "Test message 2", &empty_tree, vec![&initial_message])
+            .expect("Could not add message");
+        assert_eq!(index.update(repo).expect("Could not update index"), 1);
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.search(&"test".parse().unwrap()).len(), 2);
+
+        // moving the head to a known message changes only the tips
+        issue
+            .update_head(message.id())
+            .expect("Could not update head");
+        let tips = index.tips().clone();
+        assert_eq!(index.update(repo).expect("Could not update index"), 0);
+        assert!(*index.tips() != tips);
+    }
+}
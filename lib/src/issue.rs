@@ -13,11 +13,18 @@
 //!
 
 use git2::{self, Commit, Oid, Reference, References};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::result::Result as RResult;
+use std::str::FromStr;
 
 use error::*;
 use error::ErrorKind as EK;
+use message::Message;
+use message::accumulation::ValueAccumulator;
+use message::schema::Schema;
+use message::trailer;
+use repository::RepositoryExt;
 
 
 pub enum IssueRefType {
@@ -39,6 +46,133 @@ impl IssueRefType {
 }
 
 
+/// Order in which the messages of an issue are considered for accumulation
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum MessageOrder {
+    /// Only the messages on the first-parent chain of the head, starting with
+    /// the head, as returned by `Issue::head()`
+    FirstParent,
+    /// All messages, latest message first
+    Time,
+    /// All messages in topological order, replies before the messages they
+    /// reply to
+    Topological,
+}
+
+impl FromStr for MessageOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "first-parent"  => Ok(MessageOrder::FirstParent),
+            "time"          => Ok(MessageOrder::Time),
+            "topo"          => Ok(MessageOrder::Topological),
+            _ => Err(Error::from_kind(EK::MalformedConfig(s.to_owned()))),
+        }
+    }
+}
+
+
+/// Policy determining the messages considered for accumulation
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trust {
+    /// Consider messages from everyone
+    Everyone,
+    /// Consider only messages authored by one of the email addresses listed
+    Maintainers(Vec<String>),
+    /// Consider only messages carrying a signature
+    ///
+    /// Note that the signature is not verified.
+    Signed,
+}
+
+impl Trust {
+    /// Create a trust policy from its name
+    ///
+    /// The name is either "everyone", "maintainers" or "signed". The
+    /// maintainers' email addresses are read from the `dit.maintainers`
+    /// configuration entry, a comma separated list.
+    ///
+    pub fn from_name(name: &str, config: &git2::Config) -> Result<Self> {
+        match name {
+            "everyone"      => Ok(Trust::Everyone),
+            "maintainers"   => Ok(Trust::Maintainers(config
+                .get_string("dit.maintainers")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|m| !m.is_empty())
+                .map(String::from)
+                .collect()
+            )),
+            "signed"        => Ok(Trust::Signed),
+            _ => Err(Error::from_kind(EK::MalformedConfig(name.to_owned()))),
+        }
+    }
+
+    /// Check whether a message is trusted
+    ///
+    pub fn trusts(&self, message: &Commit) -> bool {
+        match *self {
+            Trust::Everyone => true,
+            Trust::Maintainers(ref emails) => message
+                .author()
+                .email()
+                .map(|email| emails.iter().any(|m| m == email))
+                .unwrap_or(false),
+            Trust::Signed => message
+                .raw_header()
+                .map(|header| header.lines().any(|line| line.starts_with("gpgsig ")))
+                .unwrap_or(false),
+        }
+    }
+}
+
+
+/// Selection of the messages from which the metadata of an issue is accumulated
+///
+/// The selection is configured via the following configuration entries:
+///
+///  * `dit.accumulation.order`: either "first-parent" (the default), "time" or
+///    "topo"
+///  * `dit.accumulation.trust`: either "everyone" (the default), "maintainers"
+///    or "signed"
+///  * `dit.maintainers`: a comma separated list of the maintainers' email
+///    addresses
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetadataWalk {
+    pub order: MessageOrder,
+    pub trust: Trust,
+}
+
+impl MetadataWalk {
+    /// Read the selection from a git configuration
+    ///
+    pub fn from_config(config: &git2::Config) -> Result<Self> {
+        let mut retval = MetadataWalk::default();
+
+        if let Ok(order) = config.get_string("dit.accumulation.order") {
+            retval.order = order.parse()?;
+        }
+
+        if let Ok(trust) = config.get_string("dit.accumulation.trust") {
+            retval.trust = Trust::from_name(trust.as_ref(), config)?;
+        }
+
+        Ok(retval)
+    }
+}
+
+impl Default for MetadataWalk {
+    fn default() -> Self {
+        MetadataWalk { order: MessageOrder::FirstParent, trust: Trust::Everyone }
+    }
+}
+
+
 /// Issue handle
 ///
 /// Instances of this type represent single issues. Issues reside in
@@ -92,6 +226,28 @@ impl<'r> Issue<'r> {
             .chain_err(|| EK::CannotFindIssueHead(self.id))
     }
 
+    /// Get the head of the issue
+    ///
+    /// Returns the local head of the issue, if present. Otherwise, e.g. if the
+    /// issue was only fetched, the remote head with the lowest reference name
+    /// is returned, e.g. the head of the remote whose name sorts first.
+    ///
+    pub fn head(&self) -> Result<Reference<'r>> {
+        if let Ok(head) = self.local_head() {
+            return Ok(head);
+        }
+
+        let mut heads = self
+            .heads()?
+            .collect::<RResult<Vec<_>, _>>()
+            .chain_err(|| EK::CannotFindIssueHead(self.id))?;
+        heads.sort_by(|a, b| a.name_bytes().cmp(b.name_bytes()));
+        heads
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::from_kind(EK::CannotFindIssueHead(self.id)))
+    }
+
     /// Get local references for the issue
     ///
     /// Return all references of a specific type associated with the issue from
@@ -134,28 +290,201 @@ impl<'r> Issue<'r> {
     ///
     pub fn message_revwalk(&self) -> Result<git2::Revwalk<'r>> {
         let glob = format!("**/dit/{}/**", self.ref_part());
-        self.repo
-            .revwalk()
-            .and_then(|mut revwalk| {
-                // The iterator will iterate over all the messages in the tree
-                // spanned but it will halt at the initial message.
-                revwalk.push_glob(glob.as_ref())?;
-                let _ = self.repo
-                    .find_commit(self.id)
-                    .and_then(|commit| commit.parent_id(0))
-                    .ok() // the initial message having no parent is not unusual
-                    .map(|parent| revwalk.hide(parent))
-                    .unwrap_or(Ok(()))?;
-
-                // configure the revwalk
-                revwalk.simplify_first_parent();
-                revwalk.set_sorting(git2::SORT_TOPOLOGICAL);
-
-                Ok(revwalk)
-            })
+        self.limited_revwalk(|revwalk| revwalk.push_glob(glob.as_ref()))
             .chain_err(|| EK::CannotGetReferences(glob))
     }
 
+    /// Get a revwalk for traversing the messages reachable from some commits
+    ///
+    /// The commits supplied are expected to be messages of the issue, e.g. the
+    /// new targets of references updated by a fetch. Like `message_revwalk()`,
+    /// the revwalk halts at the initial message and doesn't descend into
+    /// commits referenced by messages. The sorting of the revwalk will be set
+    /// to "topological".
+    ///
+    pub fn message_revwalk_from<I>(&self, tips: I) -> Result<git2::Revwalk<'r>>
+        where I: IntoIterator<Item = Oid>
+    {
+        self.limited_revwalk(|revwalk| {
+                for tip in tips {
+                    revwalk.push(tip)?;
+                }
+                Ok(())
+            })
+            .chain_err(|| EK::CannotGetCommit)
+    }
+
+    /// Create a revwalk limited to the messages of the issue
+    ///
+    /// The function supplied is expected to push the starting points.
+    ///
+    fn limited_revwalk<F>(&self, push: F) -> RResult<git2::Revwalk<'r>, git2::Error>
+        where F: FnOnce(&mut git2::Revwalk<'r>) -> RResult<(), git2::Error>
+    {
+        let mut revwalk = self.repo.revwalk()?;
+
+        // The iterator will iterate over all the messages in the tree spanned
+        // but it will halt at the initial message.
+        push(&mut revwalk)?;
+        let _ = self.repo
+            .find_commit(self.id)
+            .and_then(|commit| commit.parent_id(0))
+            .ok() // the initial message having no parent is not unusual
+            .map(|parent| revwalk.hide(parent))
+            .unwrap_or(Ok(()))?;
+
+        // configure the revwalk
+        revwalk.simplify_first_parent();
+        revwalk.set_sorting(git2::SORT_TOPOLOGICAL);
+
+        Ok(revwalk)
+    }
+
+    /// Get the metadata of the issue
+    ///
+    /// The metadata is accumulated from the messages selected by the walk
+    /// supplied, using the types and policies declared in the schema supplied.
+    /// The walk may be read from the configuration via
+    /// `MetadataWalk::from_config()`.
+    ///
+    pub fn metadata(&self, schema: &Schema, walk: &MetadataWalk) -> Result<BTreeMap<String, ValueAccumulator>> {
+        self.metadata_messages(walk)
+            .map(|messages| schema.accumulate(messages.into_iter().flat_map(|m| m.trailers())))
+    }
+
+    /// Get the messages from which metadata is accumulated
+    ///
+    /// The messages are selected and ordered as specified by the walk
+    /// supplied. They are returned in reverse order, e.g. the latest message
+    /// first, as expected by accumulators. If the first parents are followed,
+    /// the walk starts at the head returned by `head()`, e.g. at a remote head
+    /// if the issue has no local head.
+    ///
+    pub fn metadata_messages(&self, walk: &MetadataWalk) -> Result<Vec<Commit<'r>>> {
+        let messages : Vec<Commit<'r>> = match walk.order {
+            MessageOrder::FirstParent => {
+                let head = self
+                    .head()?
+                    .target()
+                    .ok_or_else(|| Error::from_kind(EK::CannotFindIssueHead(self.id)))
+                    .and_then(|id| self.repo.find_commit(id).chain_err(|| EK::CannotGetCommit))?;
+                self.repo.issue_messages_iter(head)?.collect::<Result<_>>()?
+            },
+            MessageOrder::Time | MessageOrder::Topological => {
+                let mut revwalk = self.message_revwalk()?;
+                if let MessageOrder::Time = walk.order {
+                    revwalk.set_sorting(git2::SORT_TIME);
+                }
+                revwalk
+                    .map(|id| id.and_then(|id| self.repo.find_commit(id)))
+                    .collect::<RResult<_, _>>()
+                    .chain_err(|| EK::CannotGetCommit)?
+            },
+        };
+
+        Ok(messages.into_iter().filter(|m| walk.trust.trusts(m)).collect())
+    }
+
+    /// Get the latest message of the issue
+    ///
+    /// Returns the message with the most recent commit time among all messages
+    /// of the issue, e.g. the last activity. Messages reachable from any head
+    /// or leaf, both local and remote, are considered.
+    ///
+    pub fn latest_message(&self) -> Result<Commit<'r>> {
+        let mut revwalk = self.message_revwalk()?;
+        revwalk.set_sorting(git2::SORT_TIME);
+        revwalk
+            .next()
+            .unwrap_or(Ok(self.id))
+            .and_then(|id| self.repo.find_commit(id))
+            .chain_err(|| EK::CannotGetCommit)
+    }
+
+    /// Get the messages of the issue created after a given time
+    ///
+    /// The time is given in seconds since the epoch and compared against the
+    /// commit time of the messages. As for `latest_message`, messages from all
+    /// heads and leaves are considered. The messages are returned in reverse
+    /// chronological order.
+    ///
+    pub fn messages_since(&self, time: i64) -> Result<Vec<Commit<'r>>> {
+        let mut revwalk = self.message_revwalk()?;
+        revwalk.set_sorting(git2::SORT_TIME);
+
+        let mut messages = Vec::new();
+        for id in revwalk {
+            let message = id
+                .and_then(|id| self.repo.find_commit(id))
+                .chain_err(|| EK::CannotGetCommit)?;
+            if message.time().seconds() > time {
+                messages.push(message);
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Get the messages of the issue not reachable from the commits supplied
+    ///
+    /// The commits may be the targets of references recorded earlier, e.g. of
+    /// references of other issues. Commits which don't exist in the repository
+    /// are ignored. The messages are returned in reverse chronological order.
+    ///
+    pub fn messages_unknown_to(&self, known: &[Oid]) -> Result<Vec<Commit<'r>>> {
+        let mut revwalk = self.message_revwalk()?;
+        revwalk.set_sorting(git2::SORT_TIME);
+        for id in known.iter().filter(|id| self.repo.find_commit(**id).is_ok()) {
+            revwalk.hide(*id).chain_err(|| EK::CannotGetCommit)?;
+        }
+
+        revwalk
+            .map(|id| id.and_then(|id| self.repo.find_commit(id)).chain_err(|| EK::CannotGetCommit))
+            .collect()
+    }
+
+    /// Get the base of the patch set
+    ///
+    /// If the issue is a patch set, e.g. the initial message carries a
+    /// `Dit-patch-base` trailer, this function returns the id of the commit on
+    /// top of which the series was submitted. For other issues, `None` is
+    /// returned.
+    ///
+    pub fn patch_set_base(&self) -> Result<Option<Oid>> {
+        let base = self
+            .initial_message()?
+            .trailers()
+            .filter(|t| t.key.as_ref() == trailer::PATCH_BASE_KEY)
+            .map(|t| t.value.to_string())
+            .next();
+
+        match base {
+            Some(base) => Oid::from_str(base.as_ref())
+                .map(Some)
+                .chain_err(|| EK::OidFormatError(base)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get a revwalk over the commits of the patch set
+    ///
+    /// The tip of a patch set is the first parent of the issue's initial
+    /// message. If the issue is a patch set, this function returns a revwalk
+    /// yielding the commits of the series, starting with the oldest one. For
+    /// other issues, `None` is returned.
+    ///
+    pub fn patch_set_revwalk(&self) -> Result<Option<git2::Revwalk<'r>>> {
+        let base = match self.patch_set_base()? {
+            Some(base) => base,
+            None => return Ok(None),
+        };
+        let tip = self
+            .initial_message()?
+            .parent_id(0)
+            .chain_err(|| EK::CannotGetCommit)?;
+
+        self.repo.series_revwalk(base, tip).map(Some)
+    }
+
     /// Add a new message to the issue
     ///
     /// Adds a new message to the issue. Also create a leaf reference for the
@@ -372,6 +701,358 @@ mod tests {
         assert!(iter2.next().is_none());
     }
 
+    #[test]
+    fn metadata() {
+        use message::accumulation::AccumulationPolicy;
+        use message::schema::{KeySpec, ValueType};
+
+        let mut testing_repo = TestingRepo::new("metadata");
+        let repo = testing_repo.repo();
+
+        let sig = git2::Signature::now("Foo Bar", "foo.bar@example.com")
+            .expect("Could not create signature");
+        let empty_tree = repo
+            .empty_tree()
+            .expect("Could not create empty tree");
+
+        let issue = repo
+            .create_issue(&sig, &sig, "Test message 1\n\nDit-status: open", &empty_tree, vec![])
+            .expect("Could not create issue");
+        let initial_message = issue
+            .initial_message()
+            .expect("Could not retrieve initial message");
+        let message = issue
+            .add_message(&sig, &sig, "Test message 2\n\nDit-status: closed\nDit-priority: 3", &empty_tree, vec![&initial_message])
+            .expect("Could not add message");
+        issue
+            .update_head(message.id())
+            .expect("Could not update head reference");
+
+        let mut schema = Schema::default();
+        schema.insert(String::from("Dit-status"), KeySpec {
+            value_type: ValueType::String,
+            values: vec![],
+            policy: AccumulationPolicy::Latest,
+        });
+
+        let mut metadata = issue
+            .metadata(&schema, &MetadataWalk::default())
+            .expect("Could not accumulate metadata");
+        {
+            let mut status = metadata
+                .remove("Dit-status")
+                .expect("Could not retrieve status")
+                .into_iter();
+            assert_eq!(status.next().unwrap().to_string(), "closed");
+            assert!(status.next().is_none());
+        }
+        {
+            let mut priority = metadata
+                .remove("Dit-priority")
+                .expect("Could not retrieve priority")
+                .into_iter();
+            assert_eq!(priority.next().unwrap().to_string(), "3");
+            assert!(priority.next().is_none());
+        }
+        assert!(metadata.is_empty());
+    }
+
+    #[test]
+    fn metadata_messages() {
+        let mut testing_repo = TestingRepo::new("metadata_messages");
+        let repo = testing_repo.repo();
+
+        let sig = git2::Signature::now("Foo Bar", "foo.bar@example.com")
+            .expect("Could not create signature");
+        let other_sig = git2::Signature::now("Mee Seeks", "meeseeks@rm.com")
+            .expect("Could not create signature");
+        let empty_tree = repo
+            .empty_tree()
+            .expect("Could not create empty tree");
+
+        let issue = repo
+            .create_issue(&sig, &sig, "Test message 1", &empty_tree, vec![])
+            .expect("Could not create issue");
+        let initial_message = issue
+            .initial_message()
+            .expect("Could not retrieve initial message");
+        let reply = issue
+            .add_message(&other_sig, &other_sig, "Test message 2\n\nDit-label: ui", &empty_tree, vec![&initial_message])
+            .expect("Could not add message");
+
+        // the head does not descend from the reply
+        let walk = MetadataWalk::default();
+        let messages = issue.metadata_messages(&walk).expect("Could not get messages");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id(), issue.id());
+
+        let mut walk = MetadataWalk { order: MessageOrder::Topological, trust: Trust::Everyone };
+        let messages = issue.metadata_messages(&walk).expect("Could not get messages");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].id(), reply.id());
+        assert_eq!(messages[1].id(), issue.id());
+
+        walk.trust = Trust::Maintainers(vec![String::from("foo.bar@example.com")]);
+        let messages = issue.metadata_messages(&walk).expect("Could not get messages");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id(), issue.id());
+
+        walk.trust = Trust::Signed;
+        let messages = issue.metadata_messages(&walk).expect("Could not get messages");
+        assert!(messages.is_empty());
+
+        // issues without a local head are accumulated from the head of the
+        // remote whose name sorts first
+        repo.reference(&format!("refs/remotes/origin/dit/{}/head", issue.ref_part()), reply.id(), false, "Remote head")
+            .expect("Could not create remote head");
+        repo.reference(&format!("refs/remotes/upstream/dit/{}/head", issue.ref_part()), issue.id(), false, "Remote head")
+            .expect("Could not create remote head");
+        issue.local_head()
+            .expect("Could not retrieve local head")
+            .delete()
+            .expect("Could not delete local head");
+        assert_eq!(issue.head().expect("Could not retrieve head").target(), Some(reply.id()));
+        let messages = issue.metadata_messages(&MetadataWalk::default()).expect("Could not get messages");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].id(), reply.id());
+    }
+
+    #[test]
+    fn latest_message() {
+        let mut testing_repo = TestingRepo::new("latest_message");
+        let repo = testing_repo.repo();
+
+        let sig = git2::Signature::new("Foo Bar", "foo.bar@example.com", &git2::Time::new(1000, 0))
+            .expect("Could not create signature");
+        let later_sig = git2::Signature::new("Foo Bar", "foo.bar@example.com", &git2::Time::new(2000, 0))
+            .expect("Could not create signature");
+        let empty_tree = repo
+            .empty_tree()
+            .expect("Could not create empty tree");
+
+        let issue = repo
+            .create_issue(&sig, &sig, "Test message 1", &empty_tree, vec![])
+            .expect("Could not create issue");
+        assert_eq!(issue.latest_message().unwrap().id(), issue.id());
+
+        let initial_message = issue
+            .initial_message()
+            .expect("Could not retrieve initial message");
+        let reply = issue
+            .add_message(&later_sig, &later_sig, "Test message 2", &empty_tree, vec![&initial_message])
+            .expect("Could not add message");
+        issue
+            .add_message(&sig, &sig, "Test message 3", &empty_tree, vec![&initial_message])
+            .expect("Could not add message");
+        assert_eq!(issue.latest_message().unwrap().id(), reply.id());
+    }
+
+    #[test]
+    fn messages_since() {
+        let mut testing_repo = TestingRepo::new("messages_since");
+        let repo = testing_repo.repo();
+
+        let sig = git2::Signature::new("Foo Bar", "foo.bar@example.com", &git2::Time::new(1000, 0))
+            .expect("Could not create signature");
+        let later_sig = git2::Signature::new("Foo Bar", "foo.bar@example.com", &git2::Time::new(2000, 0))
+            .expect("Could not create signature");
+        let empty_tree = repo
+            .empty_tree()
+            .expect("Could not create empty tree");
+
+        let issue = repo
+            .create_issue(&sig, &sig, "Test message 1", &empty_tree, vec![])
+            .expect("Could not create issue");
+        let initial_message = issue
+            .initial_message()
+            .expect("Could not retrieve initial message");
+        let reply = issue
+            .add_message(&later_sig, &later_sig, "Test message 2", &empty_tree, vec![&initial_message])
+            .expect("Could not add message");
+
+        let ids = |time| -> Vec<Oid> {
+            issue
+                .messages_since(time)
+                .expect("Could not get messages")
+                .into_iter()
+                .map(|message| message.id())
+                .collect()
+        };
+        assert_eq!(ids(0), vec![reply.id(), issue.id()]);
+        assert_eq!(ids(1000), vec![reply.id()]);
+        assert!(ids(2000).is_empty());
+    }
+
+    #[test]
+    fn messages_unknown_to() {
+        let mut testing_repo = TestingRepo::new("messages_unknown_to");
+        let repo = testing_repo.repo();
+
+        let sig = git2::Signature::now("Foo Bar", "foo.bar@example.com")
+            .expect("Could not create signature");
+        let empty_tree = repo
+            .empty_tree()
+            .expect("Could not create empty tree");
+
+        let issue = repo
+            .create_issue(&sig, &sig, "Test message 1", &empty_tree, vec![])
+            .expect("Could not create issue");
+        let initial_message = issue
+            .initial_message()
+            .expect("Could not retrieve initial message");
+        let reply = issue
+            .add_message(&sig, &sig, "Test message 2", &empty_tree, vec![&initial_message])
+            .expect("Could not add message");
+
+        let ids = |known: &[Oid]| -> Vec<Oid> {
+            issue
+                .messages_unknown_to(known)
+                .expect("Could not get messages")
+                .into_iter()
+                .map(|message| message.id())
+                .collect()
+        };
+        let missing = Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+        assert_eq!(ids(&[]).len(), 2);
+        assert_eq!(ids(&[issue.id(), missing]), vec![reply.id()]);
+        assert!(ids(&[reply.id()]).is_empty());
+    }
+
+    #[test]
+    fn metadata_walk_from_config() {
+        let mut testing_repo = TestingRepo::new("metadata_walk_from_config");
+        let repo = testing_repo.repo();
+
+        {
+            let mut config = repo.config().expect("Could not get config");
+            config.set_str("dit.accumulation.order", "time").unwrap();
+            config.set_str("dit.accumulation.trust", "maintainers").unwrap();
+            config.set_str("dit.maintainers", "foo.bar@example.com, meeseeks@rm.com").unwrap();
+        }
+
+        let config = repo.config().expect("Could not get config");
+        let walk = MetadataWalk::from_config(&config).expect("Could not read config");
+        assert_eq!(walk.order, MessageOrder::Time);
+        assert_eq!(walk.trust, Trust::Maintainers(vec![
+            String::from("foo.bar@example.com"),
+            String::from("meeseeks@rm.com"),
+        ]));
+
+        {
+            let mut config = repo.config().expect("Could not get config");
+            config.set_str("dit.accumulation.order", "random").unwrap();
+        }
+        let config = repo.config().expect("Could not get config");
+        assert!(MetadataWalk::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn patch_set_revwalk() {
+        let mut testing_repo = TestingRepo::new("patch_set_revwalk");
+        let repo = testing_repo.repo();
+
+        let sig = git2::Signature::now("Foo Bar", "foo.bar@example.com")
+            .expect("Could not create signature");
+        let empty_tree = repo
+            .empty_tree()
+            .expect("Could not create empty tree");
+
+        let base = repo
+            .commit(None, &sig, &sig, "Base", &empty_tree, &[])
+            .and_then(|id| repo.find_commit(id))
+            .expect("Could not create commit");
+        let patch1 = repo
+            .commit(None, &sig, &sig, "Patch 1", &empty_tree, &[&base])
+            .and_then(|id| repo.find_commit(id))
+            .expect("Could not create commit");
+        let patch2 = repo
+            .commit(None, &sig, &sig, "Patch 2", &empty_tree, &[&patch1])
+            .and_then(|id| repo.find_commit(id))
+            .expect("Could not create commit");
+
+        let bug = repo
+            .create_issue(&sig, &sig, "Test message 1", &empty_tree, vec![&patch2])
+            .expect("Could not create issue");
+        assert!(bug.patch_set_revwalk().expect("Could not create revwalk").is_none());
+
+        let message = format!("Test message 2\n\nDit-type: patch-set\nDit-patch-base: {}", base.id());
+        let patch_set = repo
+            .create_issue(&sig, &sig, message, &empty_tree, vec![&patch2])
+            .expect("Could not create issue");
+        assert_eq!(patch_set.patch_set_base().unwrap(), Some(base.id()));
+
+        let mut iter = patch_set
+            .patch_set_revwalk()
+            .expect("Could not create revwalk")
+            .expect("Issue is not a patch set");
+        assert_eq!(iter.next().unwrap().unwrap(), patch1.id());
+        assert_eq!(iter.next().unwrap().unwrap(), patch2.id());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn message_revwalk_from() {
+        let mut upstream_repo = TestingRepo::new("message_revwalk_from_upstream");
+        let upstream = upstream_repo.repo();
+
+        let sig = git2::Signature::now("Foo Bar", "foo.bar@example.com")
+            .expect("Could not create signature");
+        let empty_tree = upstream
+            .empty_tree()
+            .expect("Could not create empty tree");
+
+        let base = upstream
+            .commit(None, &sig, &sig, "Base", &empty_tree, &[])
+            .and_then(|id| upstream.find_commit(id))
+            .expect("Could not create commit");
+        let patch = upstream
+            .commit(None, &sig, &sig, "Patch 1", &empty_tree, &[&base])
+            .and_then(|id| upstream.find_commit(id))
+            .expect("Could not create commit");
+        let message = format!("Test message 1\n\nDit-type: patch-set\nDit-patch-base: {}", base.id());
+        let patch_set = upstream
+            .create_issue(&sig, &sig, message, &empty_tree, vec![&patch])
+            .expect("Could not create issue");
+        let initial_message = patch_set
+            .initial_message()
+            .expect("Could not retrieve initial message");
+
+        // fetch the patch set into another repository
+        let mut testing_repo = TestingRepo::new("message_revwalk_from");
+        let repo = testing_repo.repo();
+        let fetch = |repo: &git2::Repository| repo
+            .remote_anonymous(upstream.path().to_str().unwrap())
+            .and_then(|mut remote| remote.fetch(&["refs/dit/*:refs/remotes/origin/dit/*"], None, None))
+            .expect("Could not fetch");
+        let tips = |repo: &git2::Repository| -> Vec<Oid> {
+            Issue::new(repo, patch_set.id())
+                .remote_refs(IssueRefType::Any)
+                .expect("Could not retrieve remote refs")
+                .map(|reference| reference.unwrap().target().unwrap())
+                .collect()
+        };
+        fetch(repo);
+
+        // the commits of the series are not considered messages
+        let ids = |repo: &git2::Repository, known: &[Oid]| -> Vec<Oid> {
+            let mut revwalk = Issue::new(repo, patch_set.id())
+                .message_revwalk_from(tips(repo))
+                .expect("Could not create revwalk");
+            for id in known {
+                revwalk.hide(*id).expect("Could not hide commit");
+            }
+            revwalk.map(|id| id.unwrap()).collect()
+        };
+        assert_eq!(ids(repo, &[]), vec![patch_set.id()]);
+
+        // neither are commits referenced by replies
+        let reply = patch_set
+            .add_message(&sig, &sig, "Test message 2", &empty_tree, vec![&initial_message, &base])
+            .expect("Could not add message");
+        fetch(repo);
+        assert_eq!(ids(repo, &[patch_set.id()]), vec![reply.id()]);
+    }
+
     #[test]
     fn update_head() {
         let mut testing_repo = TestingRepo::new("update_head");
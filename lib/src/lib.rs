@@ -36,15 +36,20 @@
 
 #[macro_use] extern crate error_chain;
 #[macro_use] extern crate lazy_static;
+extern crate chrono;
 extern crate git2;
 extern crate regex;
 
 pub mod error;
+pub mod index;
 pub mod issue;
 pub mod iter;
+pub mod mailmap;
 pub mod message;
 pub mod remote;
 pub mod repository;
+pub mod search;
+pub mod stats;
 
 #[cfg(test)]
 mod test_utils;
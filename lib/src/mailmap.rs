@@ -0,0 +1,193 @@
+// git-dit - the distributed issue tracker for git
+// Copyright (C) 2017 Matthias Beyer <mail@beyermatthias.de>
+// Copyright (C) 2017 Julian Ganz <neither@nut.email>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Mailmap
+//!
+//! Git allows mapping the names and email addresses people used over time to a
+//! canonical identity via a "mailmap" file, as described in git-shortlog(1).
+//! This module provides the `Mailmap` type for resolving such identities.
+//!
+
+use regex::Regex;
+use std::str::FromStr;
+
+use error::*;
+
+
+/// Single line of a mailmap
+///
+/// An entry maps a commit email address, optionally restricted to a commit
+/// name, to a proper name and/or a proper email address.
+///
+#[derive(Clone, Debug, PartialEq)]
+struct MailmapEntry {
+    proper_name: Option<String>,
+    proper_email: Option<String>,
+    commit_name: Option<String>,
+    commit_email: String,
+}
+
+impl MailmapEntry {
+    /// Check whether the entry applies to an identity
+    ///
+    fn matches(&self, name: &str, email: &str) -> bool {
+        self.commit_email.to_lowercase() == email.to_lowercase() &&
+            self.commit_name.as_ref().map(|n| n.to_lowercase() == name.to_lowercase()).unwrap_or(true)
+    }
+}
+
+
+/// Mailmap
+///
+/// A mailmap maps the identities found in commits to canonical identities.
+///
+#[derive(Clone, Debug, Default)]
+pub struct Mailmap {
+    entries: Vec<MailmapEntry>,
+}
+
+impl Mailmap {
+    /// Get the canonical identity for a name and an email address
+    ///
+    /// If the mailmap doesn't contain an entry for the identity, the name and
+    /// email address supplied are returned.
+    ///
+    pub fn canonical(&self, name: &str, email: &str) -> (String, String) {
+        // entries restricted to a commit name take precedence
+        let entry = self.entries
+            .iter()
+            .filter(|entry| entry.matches(name, email))
+            .max_by_key(|entry| entry.commit_name.is_some());
+
+        match entry {
+            Some(entry) => (
+                entry.proper_name.clone().unwrap_or_else(|| name.to_owned()),
+                entry.proper_email.clone().unwrap_or_else(|| email.to_owned()),
+            ),
+            None => (name.to_owned(), email.to_owned()),
+        }
+    }
+
+    /// Look up a canonical identity by either name or email address
+    ///
+    /// The query is compared case-insensitively to both the proper and the
+    /// commit names and email addresses of all entries. An identity is only
+    /// returned if the matching entry provides both a name and an email
+    /// address.
+    ///
+    pub fn lookup(&self, query: &str) -> Option<(String, String)> {
+        let query = query.to_lowercase();
+        let is_query = |value: &Option<String>| value
+            .as_ref()
+            .map(|v| v.to_lowercase() == query)
+            .unwrap_or(false);
+
+        self.entries
+            .iter()
+            .filter(|entry| {
+                is_query(&entry.proper_name) || is_query(&entry.proper_email) ||
+                    is_query(&entry.commit_name) || entry.commit_email.to_lowercase() == query
+            })
+            .filter_map(|entry| entry
+                .proper_name
+                .clone()
+                .or_else(|| entry.commit_name.clone())
+                .map(|name| (name, entry.proper_email.clone().unwrap_or_else(|| entry.commit_email.clone())))
+            )
+            .next()
+    }
+}
+
+impl FromStr for Mailmap {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        lazy_static! {
+            // regex matching a single entry, e.g.
+            // "Proper Name <proper@email.xx> Commit Name <commit@email.xx>"
+            static ref ENTRY: Regex = Regex::new(
+                r"^\s*([^<]*?)\s*<([^>]*)>\s*(?:([^<]*?)\s*<([^>]*)>)?\s*$"
+            ).unwrap();
+        }
+
+        let non_empty = |s: &str| if s.is_empty() { None } else { Some(s.to_owned()) };
+
+        let entries = s
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .filter_map(|line| ENTRY.captures(line))
+            .map(|c| {
+                let get = |i| c.get(i).map(|m| m.as_str()).unwrap_or("");
+                if c.get(4).is_some() {
+                    MailmapEntry {
+                        proper_name: non_empty(get(1)),
+                        proper_email: non_empty(get(2)),
+                        commit_name: non_empty(get(3)),
+                        commit_email: get(4).to_owned(),
+                    }
+                } else {
+                    MailmapEntry {
+                        proper_name: non_empty(get(1)),
+                        proper_email: None,
+                        commit_name: None,
+                        commit_email: get(2).to_owned(),
+                    }
+                }
+            })
+            .collect();
+
+        Ok(Mailmap { entries: entries })
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_mailmap() -> Mailmap {
+        Mailmap::from_str(
+            "# Test mailmap\n\
+             Foo Bar <foo.bar@example.com>\n\
+             <foo@example.com> <foo.bar@old.example.com>\n\
+             Mee Seeks <meeseeks@rm.com> Mr Meeseeks <box@rm.com> # comment\n\
+             Mee Seeks <meeseeks@rm.com> <mee@rm.com>\n"
+        ).expect("Could not parse mailmap")
+    }
+
+    // Mailmap tests
+
+    #[test]
+    fn canonical() {
+        let mailmap = test_mailmap();
+        assert_eq!(mailmap.canonical("foo", "foo.bar@example.com"),
+                   (String::from("Foo Bar"), String::from("foo.bar@example.com")));
+        assert_eq!(mailmap.canonical("Foo Bar", "foo.bar@old.example.com"),
+                   (String::from("Foo Bar"), String::from("foo@example.com")));
+        assert_eq!(mailmap.canonical("Mr Meeseeks", "box@rm.com"),
+                   (String::from("Mee Seeks"), String::from("meeseeks@rm.com")));
+        assert_eq!(mailmap.canonical("Someone", "box@rm.com"),
+                   (String::from("Someone"), String::from("box@rm.com")));
+        assert_eq!(mailmap.canonical("Rick", "rick@rm.com"),
+                   (String::from("Rick"), String::from("rick@rm.com")));
+    }
+
+    #[test]
+    fn lookup() {
+        let mailmap = test_mailmap();
+        assert_eq!(mailmap.lookup("foo bar"),
+                   Some((String::from("Foo Bar"), String::from("foo.bar@example.com"))));
+        assert_eq!(mailmap.lookup("mee@rm.com"),
+                   Some((String::from("Mee Seeks"), String::from("meeseeks@rm.com"))));
+        assert_eq!(mailmap.lookup("foo@example.com"), None);
+        assert_eq!(mailmap.lookup("Rick"), None);
+    }
+}
@@ -16,7 +16,10 @@
 
 use std::collections;
 use std::hash::BuildHasher;
+use std::str::FromStr;
 
+use error::*;
+use error::ErrorKind as EK;
 use message::trailer::{Trailer, TrailerValue};
 
 /// Policy for accumulating trailers
@@ -24,9 +27,37 @@ use message::trailer::{Trailer, TrailerValue};
 /// These enum values represent accumulation policies for trailers, e.g. how
 /// trailer values are accumulated.
 ///
+#[derive(Clone, Debug, PartialEq)]
 pub enum AccumulationPolicy {
+    /// Retain only the latest value
     Latest,
+    /// Retain all values
     List,
+    /// Retain only the oldest value
+    First,
+    /// Retain the set of values added but not removed
+    ///
+    /// Values prefixed with `+` are added to the set while values prefixed
+    /// with `-` are removed. Values without a prefix are added. Each item of a
+    /// list is treated as an individual value.
+    Union,
+    /// Count the occurrences of the trailer
+    Count,
+}
+
+impl FromStr for AccumulationPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "latest"    => Ok(AccumulationPolicy::Latest),
+            "list"      => Ok(AccumulationPolicy::List),
+            "first"     => Ok(AccumulationPolicy::First),
+            "union"     => Ok(AccumulationPolicy::Union),
+            "count"     => Ok(AccumulationPolicy::Count),
+            _ => Err(Error::from_kind(EK::MalformedSchema(s.to_owned()))),
+        }
+    }
 }
 
 
@@ -35,9 +66,16 @@ pub enum AccumulationPolicy {
 /// This type encapsulates the task of accumulating trailers in an appropriate
 /// data structure.
 ///
+/// Note that values are expected to be processed in reverse chronological
+/// order, e.g. starting with the latest value, as is the case when traversing
+/// the messages of an issue from its head.
+///
 pub enum ValueAccumulator {
     Latest(Option<TrailerValue>),
     List(Vec<TrailerValue>),
+    First(Option<TrailerValue>),
+    Union(collections::BTreeMap<TrailerValue, bool>),
+    Count(usize),
 }
 
 impl ValueAccumulator {
@@ -49,6 +87,53 @@ impl ValueAccumulator {
                 *value = Some(new_value);
             },
             &mut ValueAccumulator::List(ref mut values)  => values.push(new_value),
+            &mut ValueAccumulator::First(ref mut value)  => *value = Some(new_value),
+            &mut ValueAccumulator::Union(ref mut values) => {
+                let items = match new_value {
+                    TrailerValue::List(items) => items,
+                    value => vec![value],
+                };
+
+                // Since we process the latest values first, only the first
+                // occurance of an item determines whether it is in the set.
+                for item in items {
+                    let (item, present) = Self::union_item(item);
+                    values.entry(item).or_insert(present);
+                }
+            },
+            &mut ValueAccumulator::Count(ref mut count)  => *count += 1,
+        }
+    }
+
+    /// Get an iterator over references to the accumulated values
+    ///
+    /// Note that `Count` accumulators don't hold any values. Hence, the
+    /// iterator returned for such an accumulator is empty.
+    ///
+    pub fn iter<'a>(&'a self) -> Box<Iterator<Item = &'a TrailerValue> + 'a> {
+        match *self {
+            ValueAccumulator::Latest(ref value) => Box::new(value.iter()),
+            ValueAccumulator::List(ref values)  => Box::new(values.iter()),
+            ValueAccumulator::First(ref value)  => Box::new(value.iter()),
+            ValueAccumulator::Union(ref values) => Box::new(values
+                .iter()
+                .filter(|&(_, present)| *present)
+                .map(|(value, _)| value)
+            ),
+            ValueAccumulator::Count(_)          => Box::new(None.into_iter()),
+        }
+    }
+
+    /// Split an item processed by a `Union` accumulator into the value and
+    /// whether it is added or removed
+    ///
+    fn union_item(item: TrailerValue) -> (TrailerValue, bool) {
+        let string = item.to_string();
+        let mut chars = string.chars();
+        match chars.next() {
+            Some('+') => (TrailerValue::from_slice(chars.as_str().trim()), true),
+            Some('-') => (TrailerValue::from_slice(chars.as_str().trim()), false),
+            _         => (item, true),
         }
     }
 }
@@ -58,6 +143,9 @@ impl From<AccumulationPolicy> for ValueAccumulator {
         match policy {
             AccumulationPolicy::Latest  => ValueAccumulator::Latest(None),
             AccumulationPolicy::List    => ValueAccumulator::List(Vec::new()),
+            AccumulationPolicy::First   => ValueAccumulator::First(None),
+            AccumulationPolicy::Union   => ValueAccumulator::Union(collections::BTreeMap::new()),
+            AccumulationPolicy::Count   => ValueAccumulator::Count(0),
         }
     }
 }
@@ -70,6 +158,13 @@ impl IntoIterator for ValueAccumulator {
         match self {
             ValueAccumulator::Latest(value) => Box::new(value.into_iter()),
             ValueAccumulator::List(values)  => Box::new(values.into_iter()),
+            ValueAccumulator::First(value)  => Box::new(value.into_iter()),
+            ValueAccumulator::Union(values) => Box::new(values
+                .into_iter()
+                .filter(|&(_, present)| present)
+                .map(|(value, _)| value)
+            ),
+            ValueAccumulator::Count(count)  => Box::new(Some(TrailerValue::Int(count as i64)).into_iter()),
         }
     }
 }
@@ -238,6 +333,47 @@ mod tests {
         assert_eq!(values.next(), None);
     }
 
+    #[test]
+    fn accumulate_first() {
+        let mut acc = ValueAccumulator::from(AccumulationPolicy::First);
+        acc.process(TrailerValue::from_slice("foo-bar"));
+        acc.process(TrailerValue::from_slice("baz"));
+
+        let mut values = acc.into_iter();
+        assert_eq!(values.next().expect("Could not retrieve value").to_string(), "baz");
+        assert_eq!(values.next(), None);
+    }
+
+    #[test]
+    fn accumulate_union() {
+        let mut acc = ValueAccumulator::from(AccumulationPolicy::Union);
+        // latest values first
+        acc.process(TrailerValue::from_slice("-ui"));
+        acc.process(TrailerValue::from_slice("+docs"));
+        acc.process(TrailerValue::List(vec![
+            TrailerValue::from_slice("ui"),
+            TrailerValue::from_slice("-core"),
+        ]));
+        acc.process(TrailerValue::from_slice("core"));
+
+        assert_eq!(acc.iter().collect::<Vec<_>>(), vec![&TrailerValue::from_slice("docs")]);
+
+        let mut values = acc.into_iter();
+        assert_eq!(values.next().expect("Could not retrieve value").to_string(), "docs");
+        assert_eq!(values.next(), None);
+    }
+
+    #[test]
+    fn accumulate_count() {
+        let mut acc = ValueAccumulator::from(AccumulationPolicy::Count);
+        acc.process(TrailerValue::from_slice("foo-bar"));
+        acc.process(TrailerValue::from_slice("baz"));
+
+        let mut values = acc.into_iter();
+        assert_eq!(values.next().expect("Could not retrieve value"), TrailerValue::Int(2));
+        assert_eq!(values.next(), None);
+    }
+
     // Accumulator tests
 
     #[test]
@@ -289,6 +425,39 @@ mod tests {
         assert!(acc.is_empty());
     }
 
+    #[test]
+    fn btree_map_accumulator_policies() {
+        use std::iter::FromIterator;
+
+        let val_accs = vec![
+            (String::from("Reporter"), AccumulationPolicy::First),
+            (String::from("Label"), AccumulationPolicy::Union),
+            (String::from("Bump"), AccumulationPolicy::Count),
+        ]
+            .into_iter()
+            .map(|(k, v)| (k, ValueAccumulator::from(v)));
+        let mut acc = ::std::collections::BTreeMap::from_iter(val_accs);
+
+        acc.process(Trailer::new("Label", "-ui"));
+        acc.process(Trailer::new("Bump", "please"));
+        acc.process(Trailer::new("Reporter", "Mee Seeks <meeseeks@rm.com>"));
+        acc.process(Trailer::new("Label", "+core"));
+        acc.process(Trailer::new("Bump", "please"));
+        acc.process(Trailer::new("Label", "ui"));
+        acc.process(Trailer::new("Reporter", "Foo Bar <foo.bar@example.com>"));
+
+        let mut values = |key: &str| acc
+            .remove(&String::from(key))
+            .expect("Could not retrieve value from map")
+            .into_iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(values("Reporter"), vec!["Foo Bar <foo.bar@example.com>"]);
+        assert_eq!(values("Label"), vec!["core"]);
+        assert_eq!(values("Bump"), vec!["2"]);
+    }
+
     #[test]
     fn single_accumulator_latest() {
         let mut acc = SingleAccumulator::new(String::from("Foo-bar"), AccumulationPolicy::Latest);
@@ -0,0 +1,593 @@
+// git-dit - the distributed issue tracker for git
+// Copyright (C) 2017 Matthias Beyer <mail@beyermatthias.de>
+// Copyright (C) 2017 Julian Ganz <neither@nut.email>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Metadata schema
+//!
+//! A repository may declare the metadata keys known to its issues, the type of
+//! the values and the values allowed for each key, as well as the policy used
+//! for accumulating the values. This module provides the `Schema` type holding
+//! such a declaration, which may be used for validating trailers.
+//!
+//! The schema is read from the git configuration. Each key is declared in a
+//! subsection of `dit.metadata`, e.g.:
+//!
+//! ```ignore
+//! [dit "metadata.Dit-status"]
+//!     type = enum
+//!     values = open,closed,wontfix
+//!     policy = latest
+//! ```
+//!
+
+use git2;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use error::*;
+use error::ErrorKind as EK;
+use message::accumulation::{AccumulationPolicy, Accumulator, ValueAccumulator};
+use message::trailer::{self, Trailer, TrailerValue};
+
+
+/// Prefix of the configuration entries declaring the schema
+///
+const CONFIG_PREFIX: &'static str = "dit.metadata.";
+
+/// Prefix of the keys of trailers specific to git-dit
+///
+const DIT_KEY_PREFIX: &'static str = "Dit-";
+
+/// Keys managed by git-dit itself
+///
+/// Trailers with these keys are never subject to validation.
+///
+const RESERVED_KEYS: &'static [&'static str] = &[
+    trailer::TYPE_KEY,
+    trailer::PATCH_BASE_KEY,
+    trailer::DUPLICATE_KEY,
+    trailer::MOVED_TO_KEY,
+    trailer::MOVED_FROM_KEY,
+];
+
+
+/// Type of the values of a piece of metadata
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValueType {
+    /// Arbitrary string
+    String,
+    /// Integer
+    Int,
+    /// One out of the allowed values
+    Enum,
+    /// Person in the form `Name <email>`
+    Person,
+    /// Date, either in RFC 3339 format or in the form `YYYY-MM-DD`
+    Date,
+    /// Boolean, e.g. `true` or `false`
+    Bool,
+    /// Full hexadecimal object id
+    Oid,
+    /// Comma separated list of values
+    List,
+}
+
+impl ValueType {
+    /// Check whether a string is a valid representation of a value
+    ///
+    /// Items of lists as well as enum values are checked against the allowed
+    /// values supplied, unless no allowed values are supplied.
+    ///
+    pub fn is_valid(&self, value: &str, allowed: &[String]) -> bool {
+        let is_allowed = |value: &str| allowed.is_empty() || allowed.iter().any(|v| v == value);
+        match *self {
+            ValueType::Enum => allowed.iter().any(|v| v == value),
+            ValueType::List => value
+                .split(',')
+                .map(str::trim)
+                .all(|item| !item.is_empty() && is_allowed(item)),
+            ValueType::String |
+            ValueType::Int => self.parse(value).is_some() && is_allowed(value),
+            ValueType::Person |
+            ValueType::Date |
+            ValueType::Bool |
+            ValueType::Oid => self.parse(value).is_some(),
+        }
+    }
+
+    /// Parse a value of this type from its string representation
+    ///
+    /// Items of lists are parsed as integers or strings, just like values of
+    /// trailers with an unknown type.
+    ///
+    pub fn parse(&self, value: &str) -> Option<TrailerValue> {
+        match *self {
+            ValueType::String |
+            ValueType::Enum   => Some(TrailerValue::String(value.to_owned())),
+            ValueType::Int    => i64::from_str(value).ok().map(TrailerValue::Int),
+            ValueType::Person => TrailerValue::person_from_slice(value),
+            ValueType::Date   => TrailerValue::date_from_slice(value),
+            ValueType::Bool   => TrailerValue::bool_from_slice(value),
+            ValueType::Oid    => TrailerValue::oid_from_slice(value),
+            ValueType::List   => TrailerValue::list_from_slice(value, |item| {
+                Some(TrailerValue::from_slice(item))
+            }),
+        }
+    }
+
+    /// Get the type conventionally associated with a key
+    ///
+    /// Keys ending in `-by`, e.g. `Signed-off-by`, as well as the keys
+    /// `Dit-assignee` and `Dit-reporter` hold persons. `Dit-due` and keys
+    /// ending in `-date` hold dates. The base of a patch set is an object id and
+    /// labels are lists.
+    ///
+    pub fn for_key(key: &str) -> Option<ValueType> {
+        let key = key.to_lowercase();
+        if key.ends_with("-by") ||
+            key == trailer::ASSIGNEE_KEY.to_lowercase() ||
+            key == trailer::REPORTER_KEY.to_lowercase() {
+            Some(ValueType::Person)
+        } else if key.ends_with("-date") || key == trailer::DUE_KEY.to_lowercase() {
+            Some(ValueType::Date)
+        } else if key == trailer::PATCH_BASE_KEY.to_lowercase() || key == trailer::DUPLICATE_KEY.to_lowercase() {
+            Some(ValueType::Oid)
+        } else if key == trailer::LABEL_KEY.to_lowercase() {
+            Some(ValueType::List)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for ValueType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "string"    => Ok(ValueType::String),
+            "int"       => Ok(ValueType::Int),
+            "enum"      => Ok(ValueType::Enum),
+            "person"    => Ok(ValueType::Person),
+            "date"      => Ok(ValueType::Date),
+            "bool"      => Ok(ValueType::Bool),
+            "oid"       => Ok(ValueType::Oid),
+            "list"      => Ok(ValueType::List),
+            _ => Err(Error::from_kind(EK::MalformedSchema(s.to_owned()))),
+        }
+    }
+}
+
+
+/// Declaration of a single piece of metadata
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeySpec {
+    pub value_type: ValueType,
+    pub values: Vec<String>,
+    pub policy: AccumulationPolicy,
+}
+
+impl KeySpec {
+    /// Create a new declaration for values of the type supplied
+    ///
+    /// The declaration will allow arbitrary values and use the `Latest`
+    /// accumulation policy.
+    ///
+    pub fn new(value_type: ValueType) -> Self {
+        KeySpec {
+            value_type: value_type,
+            values: Vec::new(),
+            policy: AccumulationPolicy::Latest,
+        }
+    }
+}
+
+impl Default for KeySpec {
+    fn default() -> Self {
+        KeySpec::new(ValueType::String)
+    }
+}
+
+
+/// Metadata schema
+///
+/// A schema maps keys to declarations of the metadata. An empty schema, e.g. a
+/// schema of a repository without any declarations, accepts any trailer.
+///
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    keys: BTreeMap<String, KeySpec>,
+}
+
+impl Schema {
+    /// Read the schema from a git configuration
+    ///
+    pub fn from_config(config: &git2::Config) -> Result<Self> {
+        let mut retval = Schema::default();
+
+        let entries = config
+            .entries(Some(r"^dit\.metadata\..*"))
+            .chain_err(|| EK::MalformedSchema(CONFIG_PREFIX.to_owned()))?;
+        for entry in &entries {
+            let entry = entry.chain_err(|| EK::MalformedSchema(CONFIG_PREFIX.to_owned()))?;
+            let name = entry.name().unwrap_or("");
+            let value = entry.value().unwrap_or("");
+
+            // the name has the form "dit.metadata.<key>.<variable>", with the
+            // key potentially containing dots
+            let (key, variable) = match name[CONFIG_PREFIX.len()..].rfind('.') {
+                Some(pos) => (&name[CONFIG_PREFIX.len()..CONFIG_PREFIX.len() + pos],
+                              &name[CONFIG_PREFIX.len() + pos + 1..]),
+                None => return Err(Error::from_kind(EK::MalformedSchema(name.to_owned()))),
+            };
+
+            let spec = retval.keys.entry(key.to_owned()).or_insert_with(KeySpec::default);
+            match variable {
+                "type"      => spec.value_type = value.parse()?,
+                "values"    => spec.values.extend(value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(String::from)
+                ),
+                "policy"    => spec.policy = value.parse()?,
+                _ => return Err(Error::from_kind(EK::MalformedSchema(name.to_owned()))),
+            }
+        }
+
+        Ok(retval)
+    }
+
+    /// Add a declaration for a key to the schema
+    ///
+    pub fn insert(&mut self, key: String, spec: KeySpec) {
+        self.keys.insert(key, spec);
+    }
+
+    /// Get the declaration for a key
+    ///
+    pub fn get(&self, key: &str) -> Option<&KeySpec> {
+        self.keys.get(key)
+    }
+
+    /// Get an iterator over all the keys and their declarations
+    ///
+    pub fn iter(&self) -> ::std::collections::btree_map::Iter<String, KeySpec> {
+        self.keys.iter()
+    }
+
+    /// Check whether the schema declares any key
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Get the type of the values associated with a key
+    ///
+    /// If the key is not declared in the schema, the type conventionally
+    /// associated with the key is returned, if any.
+    ///
+    pub fn value_type(&self, key: &str) -> Option<ValueType> {
+        self.get(key)
+            .map(|spec| spec.value_type.clone())
+            .or_else(|| ValueType::for_key(key))
+    }
+
+    /// Get the accumulation policy for a key
+    ///
+    /// Unless declared otherwise, labels are accumulated as a set and only the
+    /// latest milestone, due date, priority and severity are retained. Values
+    /// of other keys which are not declared, including the status and the
+    /// assignee, are accumulated as a list.
+    ///
+    pub fn policy(&self, key: &str) -> AccumulationPolicy {
+        self.get(key)
+            .map(|spec| spec.policy.clone())
+            .unwrap_or_else(|| match key {
+                trailer::LABEL_KEY      => AccumulationPolicy::Union,
+                trailer::MILESTONE_KEY  |
+                trailer::DUE_KEY        |
+                trailer::PRIORITY_KEY   |
+                trailer::SEVERITY_KEY   |
+                trailer::DUPLICATE_KEY  |
+                trailer::MOVED_TO_KEY   |
+                trailer::MOVED_FROM_KEY => AccumulationPolicy::Latest,
+                _ => AccumulationPolicy::List,
+            })
+    }
+
+    /// Accumulate the metadata from a sequence of trailers
+    ///
+    /// The trailers are converted to their typed representation and
+    /// accumulated according to the policy of their key. The resulting map
+    /// contains one accumulator for every key encountered. As for any
+    /// accumulation, the trailers are expected in reverse chronological order.
+    ///
+    pub fn accumulate<I>(&self, trailers: I) -> BTreeMap<String, ValueAccumulator>
+        where I: IntoIterator<Item = Trailer>
+    {
+        let mut retval = BTreeMap::new();
+        for trailer in trailers.into_iter().map(|trailer| self.typed(trailer)) {
+            if !retval.contains_key(trailer.key.as_ref()) {
+                let key = trailer.key.as_ref().clone();
+                let acc = ValueAccumulator::from(self.policy(key.as_ref()));
+                retval.insert(key, acc);
+            }
+            retval.process(trailer);
+        }
+        retval
+    }
+
+    /// Convert a trailer's value to the type associated with its key
+    ///
+    /// If no type is associated with the key or if the value does not parse
+    /// as a value of that type, the trailer is returned unaltered.
+    ///
+    pub fn typed(&self, trailer: Trailer) -> Trailer {
+        let value = self
+            .value_type(trailer.key.as_ref())
+            .and_then(|value_type| value_type.parse(trailer.value.to_string().as_ref()));
+        match value {
+            Some(value) => Trailer { key: trailer.key, value: value },
+            None => trailer,
+        }
+    }
+
+    /// Validate a trailer
+    ///
+    /// A trailer is considered valid if its key is declared and its value
+    /// conforms to the declaration. Trailers with keys which are not specific
+    /// to git-dit are valid unless their key resembles a declared key, e.g. if
+    /// it differs only in case or lacks the `Dit-` prefix. Empty values, which
+    /// reset a piece of metadata, are valid for all declared keys. If the
+    /// schema is empty, all trailers are considered valid.
+    ///
+    pub fn validate(&self, trailer: &Trailer) -> Result<()> {
+        let key : &str = trailer.key.as_ref();
+        if self.is_empty() || RESERVED_KEYS.contains(&key) {
+            return Ok(());
+        }
+
+        if let Some(spec) = self.get(key) {
+            let value = trailer.value.to_string();
+            if value.is_empty() {
+                // an empty value resets the metadata
+                return Ok(());
+            }
+
+            // For sets, only the values without the "+" or "-" prefix need to
+            // conform to the declaration.
+            let stripped = if spec.policy == AccumulationPolicy::Union {
+                value.split(',')
+                     .map(|item| item.trim().trim_start_matches(|c| c == '+' || c == '-').trim())
+                     .collect::<Vec<_>>()
+                     .join(", ")
+            } else {
+                value.clone()
+            };
+
+            return if spec.value_type.is_valid(stripped.as_ref(), &spec.values) {
+                Ok(())
+            } else {
+                Err(Error::from_kind(EK::InvalidTrailerValue(key.to_owned(), value)))
+            };
+        }
+
+        // look for a declared key the user might have meant
+        let lowercase = key.to_lowercase();
+        let resembled = self.keys
+            .keys()
+            .find(|k| {
+                let k = k.to_lowercase();
+                k == lowercase || k == format!("{}{}", DIT_KEY_PREFIX.to_lowercase(), lowercase)
+            });
+        if let Some(resembled) = resembled {
+            return Err(Error::from_kind(EK::MisspelledTrailerKey(key.to_owned(), resembled.to_owned())));
+        }
+
+        if key.to_lowercase().starts_with(&DIT_KEY_PREFIX.to_lowercase()) {
+            Err(Error::from_kind(EK::UnknownTrailerKey(key.to_owned())))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::TestingRepo;
+
+    fn test_schema() -> Schema {
+        let mut schema = Schema::default();
+        schema.insert(String::from("Dit-status"), KeySpec {
+            value_type: ValueType::Enum,
+            values: vec![String::from("open"), String::from("closed")],
+            policy: AccumulationPolicy::Latest,
+        });
+        schema.insert(String::from("Dit-priority"), KeySpec::new(ValueType::Int));
+        schema.insert(String::from("Dit-assignee"), KeySpec::new(ValueType::Person));
+        schema.insert(String::from("Dit-due"), KeySpec::new(ValueType::Date));
+        schema
+    }
+
+    // ValueType tests
+
+    #[test]
+    fn valid_values() {
+        let allowed = vec![String::from("ui"), String::from("core")];
+        assert!(ValueType::Int.is_valid("12", &[]));
+        assert!(!ValueType::Int.is_valid("twelve", &[]));
+        assert!(ValueType::Enum.is_valid("ui", &allowed));
+        assert!(!ValueType::Enum.is_valid("docs", &allowed));
+        assert!(ValueType::Person.is_valid("Foo Bar <foo.bar@example.com>", &[]));
+        assert!(!ValueType::Person.is_valid("Foo Bar", &[]));
+        assert!(ValueType::Date.is_valid("2017-07-16", &[]));
+        assert!(ValueType::Date.is_valid("2017-07-16T12:00:00+02:00", &[]));
+        assert!(!ValueType::Date.is_valid("next week", &[]));
+        assert!(ValueType::List.is_valid("ui, core", &allowed));
+        assert!(!ValueType::List.is_valid("ui, docs", &allowed));
+        assert!(!ValueType::List.is_valid("ui,,core", &allowed));
+        assert!(ValueType::Bool.is_valid("true", &[]));
+        assert!(!ValueType::Bool.is_valid("sure", &[]));
+        assert!(ValueType::Oid.is_valid("bc5a0ba70e5ae1d0b18aa6d2e6d5bc7a4ac3e4b1", &[]));
+        assert!(!ValueType::Oid.is_valid("HEAD", &[]));
+    }
+
+    #[test]
+    fn typed_trailers() {
+        let schema = test_schema();
+        assert_eq!(schema.typed(Trailer::new("Dit-assignee", "Foo Bar <foo@example.com>")).value,
+                   TrailerValue::Person {
+                       name: String::from("Foo Bar"),
+                       email: String::from("foo@example.com"),
+                   });
+        assert_eq!(schema.typed(Trailer::new("Reviewed-by", "Foo Bar <foo@example.com>")).value,
+                   TrailerValue::person_from_slice("Foo Bar <foo@example.com>").unwrap());
+        assert_eq!(schema.typed(Trailer::new("Dit-due", "2017-07-16")).value,
+                   TrailerValue::date_from_slice("2017-07-16").unwrap());
+        assert_eq!(schema.typed(Trailer::new("Dit-status", "42")).value,
+                   TrailerValue::String(String::from("42")));
+        assert_eq!(schema.typed(Trailer::new("Dit-due", "someday")).value,
+                   TrailerValue::String(String::from("someday")));
+        assert_eq!(schema.typed(Trailer::new("Dit-foo", "12")).value,
+                   TrailerValue::Int(12));
+        assert_eq!(schema.typed(Trailer::new("Dit-duplicate-of", "bc5a0ba70e5ae1d0b18aa6d2e6d5bc7a4ac3e4b1")).value,
+                   TrailerValue::oid_from_slice("bc5a0ba70e5ae1d0b18aa6d2e6d5bc7a4ac3e4b1").unwrap());
+    }
+
+    // Schema tests
+
+    #[test]
+    fn validate_trailers() {
+        let schema = test_schema();
+        schema.validate(&Trailer::new("Dit-status", "open")).unwrap();
+        schema.validate(&Trailer::new("Dit-priority", "3")).unwrap();
+        schema.validate(&Trailer::new("Signed-off-by", "Foo Bar <foo.bar@example.com>")).unwrap();
+        schema.validate(&Trailer::new("Dit-patch-base", "foo")).unwrap();
+        schema.validate(&Trailer::new("Dit-assignee", "")).unwrap();
+        assert!(schema.validate(&Trailer::new("Dit-status", "resolved")).is_err());
+        assert!(schema.validate(&Trailer::new("Dit-assignee", "Foo")).is_err());
+        assert!(schema.validate(&Trailer::new("Dit-Status", "open")).is_err());
+        assert!(schema.validate(&Trailer::new("Status", "open")).is_err());
+        assert!(schema.validate(&Trailer::new("Dit-severity", "high")).is_err());
+    }
+
+    #[test]
+    fn validate_reserved_trailers() {
+        let schema = test_schema();
+        assert!(!schema.is_empty());
+        schema.validate(&Trailer::new("Dit-type", "patch-set")).unwrap();
+        schema.validate(&Trailer::new("Dit-patch-base", "bc5a0ba70e5ae1d0b18aa6d2e6d5bc7a4ac3e4b1")).unwrap();
+        schema.validate(&Trailer::new("Dit-duplicate-of", "bc5a0ba70e5ae1d0b18aa6d2e6d5bc7a4ac3e4b1")).unwrap();
+        schema.validate(&Trailer::new("Dit-moved-to", "https://example.com/repo.git")).unwrap();
+        schema.validate(&Trailer::new("Dit-moved-from", "/home/foo/repo")).unwrap();
+    }
+
+    #[test]
+    fn accumulate_metadata() {
+        let mut schema = test_schema();
+        schema.insert(String::from("Dit-label"), KeySpec {
+            value_type: ValueType::List,
+            values: vec![],
+            policy: AccumulationPolicy::Union,
+        });
+
+        let metadata = schema.accumulate(vec![
+            Trailer::new("Dit-status", "closed"),
+            Trailer::new("Dit-label", "-ui"),
+            Trailer::new("Signed-off-by", "Foo Bar <foo.bar@example.com>"),
+            Trailer::new("Dit-status", "open"),
+            Trailer::new("Dit-label", "ui, core"),
+            Trailer::new("Signed-off-by", "Mee Seeks <meeseeks@rm.com>"),
+        ]);
+
+        let values : BTreeMap<String, Vec<String>> = metadata
+            .into_iter()
+            .map(|(k, acc)| (k, acc.into_iter().map(|v| v.to_string()).collect()))
+            .collect();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values["Dit-status"], vec!["closed"]);
+        assert_eq!(values["Dit-label"], vec!["core"]);
+        assert_eq!(values["Signed-off-by"], vec![
+            "Foo Bar <foo.bar@example.com>",
+            "Mee Seeks <meeseeks@rm.com>",
+        ]);
+    }
+
+    #[test]
+    fn validate_set_trailers() {
+        let mut schema = test_schema();
+        schema.insert(String::from("Dit-label"), KeySpec {
+            value_type: ValueType::List,
+            values: vec![String::from("ui"), String::from("core")],
+            policy: AccumulationPolicy::Union,
+        });
+        schema.validate(&Trailer::new("Dit-label", "+ui")).unwrap();
+        schema.validate(&Trailer::new("Dit-label", "-ui, core")).unwrap();
+        assert!(schema.validate(&Trailer::new("Dit-label", "+docs")).is_err());
+        assert_eq!(Schema::default().policy("Dit-label"), AccumulationPolicy::Union);
+        assert_eq!(Schema::default().policy("Dit-status"), AccumulationPolicy::List);
+        assert_eq!(Schema::default().policy("Dit-due"), AccumulationPolicy::Latest);
+        assert_eq!(Schema::default().policy("Dit-priority"), AccumulationPolicy::Latest);
+        assert_eq!(Schema::default().policy("Dit-foo"), AccumulationPolicy::List);
+    }
+
+    #[test]
+    fn empty_schema() {
+        let schema = Schema::default();
+        schema.validate(&Trailer::new("Dit-Status", "whatever")).unwrap();
+    }
+
+    #[test]
+    fn schema_from_config() {
+        let mut testing_repo = TestingRepo::new("schema_from_config");
+        let repo = testing_repo.repo();
+
+        {
+            let mut config = repo.config().expect("Could not get config");
+            config.set_str("dit.metadata.Dit-status.type", "enum").unwrap();
+            config.set_str("dit.metadata.Dit-status.values", "open, closed").unwrap();
+            config.set_str("dit.metadata.Dit-label.type", "list").unwrap();
+            config.set_str("dit.metadata.Dit-label.policy", "list").unwrap();
+        }
+
+        let config = repo.config().expect("Could not get config");
+        let schema = Schema::from_config(&config).expect("Could not read schema");
+        assert_eq!(schema.get("Dit-status"), Some(&KeySpec {
+            value_type: ValueType::Enum,
+            values: vec![String::from("open"), String::from("closed")],
+            policy: AccumulationPolicy::Latest,
+        }));
+        assert_eq!(schema.get("Dit-label"), Some(&KeySpec {
+            value_type: ValueType::List,
+            values: vec![],
+            policy: AccumulationPolicy::List,
+        }));
+        assert_eq!(schema.get("Dit-type"), None);
+    }
+
+    #[test]
+    fn malformed_schema() {
+        let mut testing_repo = TestingRepo::new("malformed_schema");
+        let repo = testing_repo.repo();
+
+        {
+            let mut config = repo.config().expect("Could not get config");
+            config.set_str("dit.metadata.Dit-status.type", "colour").unwrap();
+        }
+
+        let config = repo.config().expect("Could not get config");
+        assert!(Schema::from_config(&config).is_err());
+    }
+}
@@ -14,6 +14,8 @@
 //! uses trailers as storage for issue metadata.
 //!
 
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Timelike};
+use git2::Oid;
 use regex::Regex;
 use std::collections::VecDeque;
 use std::fmt;
@@ -24,6 +26,79 @@ use error::*;
 use error::ErrorKind as EK;
 use message::line::{Line, Lines};
 
+
+/// Key of the trailer holding the type of an issue
+///
+pub const TYPE_KEY: &'static str = "Dit-type";
+
+/// Value of the type trailer marking an issue as a patch set
+///
+pub const PATCH_SET_TYPE: &'static str = "patch-set";
+
+/// Key of the trailer holding the base of a patch set
+///
+/// The value of the trailer is the id of the commit on top of which the series
+/// of commits forming the patch set was submitted.
+///
+pub const PATCH_BASE_KEY: &'static str = "Dit-patch-base";
+
+/// Key of the trailer holding the status of an issue
+///
+pub const STATUS_KEY: &'static str = "Dit-status";
+
+/// Key of the trailer holding the person assigned to an issue
+///
+/// An empty value indicates that nobody is assigned to the issue.
+///
+pub const ASSIGNEE_KEY: &'static str = "Dit-assignee";
+
+/// Key of the trailer holding the person who reported an issue
+///
+/// If absent, the author of the initial message is considered the reporter.
+///
+pub const REPORTER_KEY: &'static str = "Dit-reporter";
+
+/// Key of the trailer holding the milestone an issue is planned for
+///
+pub const MILESTONE_KEY: &'static str = "Dit-milestone";
+
+/// Key of the trailer holding the date by which an issue is due
+///
+pub const DUE_KEY: &'static str = "Dit-due";
+
+/// Key of the trailer holding the priority of an issue
+///
+pub const PRIORITY_KEY: &'static str = "Dit-priority";
+
+/// Key of the trailer holding the severity of an issue
+///
+pub const SEVERITY_KEY: &'static str = "Dit-severity";
+
+/// Key of the trailer holding the issue an issue duplicates
+///
+/// The value is the id of the original issue.
+///
+pub const DUPLICATE_KEY: &'static str = "Dit-duplicate-of";
+
+/// Key of the trailer holding the repository an issue was moved to
+///
+pub const MOVED_TO_KEY: &'static str = "Dit-moved-to";
+
+/// Key of the trailer holding the repository an issue was moved from
+///
+pub const MOVED_FROM_KEY: &'static str = "Dit-moved-from";
+
+/// Key of the trailers holding the labels of an issue
+///
+/// Labels are accumulated as a set: values prefixed with `+` add a label while
+/// values prefixed with `-` remove one.
+///
+pub const LABEL_KEY: &'static str = "Dit-label";
+
+/// Format of dates without a time
+///
+const DATE_FORMAT: &'static str = "%Y-%m-%d";
+
 /// The Key of a Trailer:
 ///
 /// ```ignore
@@ -62,12 +137,20 @@ impl fmt::Display for TrailerKey {
 ///                # This is the value
 /// ```
 ///
+/// Values parsed via `from_slice` are either integers or strings. The other
+/// variants are produced by parsing the value's string representation according
+/// to the type associated with the trailer's key, e.g. via a metadata schema.
+/// All variants are displayed in a form from which they can be parsed again.
+///
 #[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Clone)]
 pub enum TrailerValue {
     Int(i64),
     String(String),
-
-    // Maybe something like Name { name: String, email: String } ?
+    Bool(bool),
+    Person { name: String, email: String },
+    Date(DateTime<FixedOffset>),
+    Oid(Oid),
+    List(Vec<TrailerValue>),
 }
 
 impl TrailerValue {
@@ -83,6 +166,78 @@ impl TrailerValue {
         }
     }
 
+    /// Parse a boolean value
+    ///
+    /// Both "true"/"false" and "yes"/"no" are accepted.
+    ///
+    pub fn bool_from_slice(slice: &str) -> Option<TrailerValue> {
+        match slice {
+            "true" | "yes"  => Some(TrailerValue::Bool(true)),
+            "false" | "no"  => Some(TrailerValue::Bool(false)),
+            _ => None,
+        }
+    }
+
+    /// Parse a person in the form `Name <email>`
+    ///
+    pub fn person_from_slice(slice: &str) -> Option<TrailerValue> {
+        lazy_static! {
+            // regex matching a person, e.g. "Foo Bar <foo.bar@example.com>"
+            static ref PERSON: Regex = Regex::new(r"^([^<>]+) <([^<>\s]+)>$").unwrap();
+        }
+
+        PERSON.captures(slice)
+              .and_then(|c| c.get(1).and_then(|n| c.get(2).map(|e| (n, e))))
+              .map(|(name, email)| TrailerValue::Person {
+                  name: name.as_str().trim().to_owned(),
+                  email: email.as_str().to_owned(),
+              })
+    }
+
+    /// Parse a date
+    ///
+    /// The date may either be supplied in RFC 3339 format or in the form
+    /// `YYYY-MM-DD`, in which case midnight UTC is assumed.
+    ///
+    pub fn date_from_slice(slice: &str) -> Option<TrailerValue> {
+        DateTime::parse_from_rfc3339(slice)
+            .ok()
+            .or_else(|| NaiveDate::parse_from_str(slice, DATE_FORMAT)
+                .ok()
+                .map(|date| FixedOffset::east(0).from_utc_datetime(&date.and_hms(0, 0, 0))))
+            .map(TrailerValue::Date)
+    }
+
+    /// Parse a full, hexadecimal object id
+    ///
+    pub fn oid_from_slice(slice: &str) -> Option<TrailerValue> {
+        lazy_static! {
+            // regex matching a full object id
+            static ref OID: Regex = Regex::new(r"^[[:xdigit:]]{40}$").unwrap();
+        }
+
+        if OID.is_match(slice) {
+            Oid::from_str(slice).ok().map(TrailerValue::Oid)
+        } else {
+            None
+        }
+    }
+
+    /// Parse a comma separated list of values
+    ///
+    /// Each item is parsed using the function supplied. If any item fails to
+    /// parse, no value is returned.
+    ///
+    pub fn list_from_slice<F>(slice: &str, item: F) -> Option<TrailerValue>
+        where F: Fn(&str) -> Option<TrailerValue>
+    {
+        slice.split(',')
+             .map(str::trim)
+             .map(|i| if i.is_empty() { None } else { item(i) })
+             .collect::<Option<Vec<_>>>()
+             .map(TrailerValue::List)
+    }
+
     /// Append a string to an existing trailer value
     ///
     /// This method may be used to construct multi line trailer values.
@@ -90,8 +245,8 @@ impl TrailerValue {
     ///
     pub fn append(self, slice: &str) -> TrailerValue {
         TrailerValue::String(match self {
-            TrailerValue::Int(i)    => i.to_string() + slice,
             TrailerValue::String(s) => s + slice,
+            value                   => value.to_string() + slice,
         })
     }
 }
@@ -101,6 +256,26 @@ impl fmt::Display for TrailerValue {
         match *self {
             TrailerValue::Int(i)        => write!(f, "{}", i),
             TrailerValue::String(ref s) => write!(f, "{}", s),
+            TrailerValue::Bool(b)       => write!(f, "{}", b),
+            TrailerValue::Person { ref name, ref email } => write!(f, "{} <{}>", name, email),
+            TrailerValue::Date(ref d)   => if d.offset().local_minus_utc() == 0 &&
+                                              d.num_seconds_from_midnight() == 0 &&
+                                              d.nanosecond() == 0 {
+                write!(f, "{}", d.format(DATE_FORMAT))
+            } else {
+                write!(f, "{}", d.to_rfc3339())
+            },
+            TrailerValue::Oid(ref o)    => write!(f, "{}", o),
+            TrailerValue::List(ref l)   => {
+                let mut items = l.iter();
+                if let Some(item) = items.next() {
+                    write!(f, "{}", item)?;
+                }
+                for item in items {
+                    write!(f, ", {}", item)?;
+                }
+                Ok(())
+            },
         }
     }
 }
@@ -396,6 +571,63 @@ mod tests {
         assert!(Trailer::from_str("").is_err());
     }
 
+    // TrailerValue tests
+
+    #[test]
+    fn typed_values() {
+        assert_eq!(TrailerValue::bool_from_slice("yes"), Some(TrailerValue::Bool(true)));
+        assert_eq!(TrailerValue::bool_from_slice("maybe"), None);
+        assert_eq!(TrailerValue::person_from_slice("Hans Wurst <hans@wurstmail.tld>"),
+                   Some(TrailerValue::Person {
+                       name: "Hans Wurst".to_string(),
+                       email: "hans@wurstmail.tld".to_string(),
+                   }));
+        assert_eq!(TrailerValue::person_from_slice("Hans Wurst"), None);
+        assert_eq!(TrailerValue::date_from_slice("2017-07-16"),
+                   TrailerValue::date_from_slice("2017-07-16T02:00:00+02:00"));
+        assert!(TrailerValue::date_from_slice("2017-07-16T12:00:00+02:00") >
+                TrailerValue::date_from_slice("2017-07-16"));
+        assert_eq!(TrailerValue::date_from_slice("tomorrow"), None);
+        assert!(TrailerValue::oid_from_slice("bc5a0ba70e5ae1d0b18aa6d2e6d5bc7a4ac3e4b1").is_some());
+        assert_eq!(TrailerValue::oid_from_slice("bc5a0ba"), None);
+        assert_eq!(TrailerValue::list_from_slice("1, foo", |i| Some(TrailerValue::from_slice(i))),
+                   Some(TrailerValue::List(vec![
+                       TrailerValue::Int(1),
+                       TrailerValue::String("foo".to_string()),
+                   ])));
+        assert_eq!(TrailerValue::list_from_slice("a,,b", |i| Some(TrailerValue::from_slice(i))),
+                   None);
+    }
+
+    #[test]
+    fn typed_values_roundtrip() {
+        let values = vec![
+            TrailerValue::bool_from_slice("false"),
+            TrailerValue::person_from_slice("Hans Wurst <hans@wurstmail.tld>"),
+            TrailerValue::date_from_slice("2017-07-16"),
+            TrailerValue::date_from_slice("2017-07-16T12:30:00+02:00"),
+            TrailerValue::oid_from_slice("bc5a0ba70e5ae1d0b18aa6d2e6d5bc7a4ac3e4b1"),
+        ];
+
+        for value in values.into_iter().map(|v| v.expect("Could not parse value")) {
+            let string = value.to_string();
+            let parsed = match value {
+                TrailerValue::Bool(_)           => TrailerValue::bool_from_slice(&string),
+                TrailerValue::Person { .. }     => TrailerValue::person_from_slice(&string),
+                TrailerValue::Date(_)           => TrailerValue::date_from_slice(&string),
+                TrailerValue::Oid(_)            => TrailerValue::oid_from_slice(&string),
+                _ => None,
+            };
+            assert_eq!(parsed, Some(value));
+        }
+
+        assert_eq!(TrailerValue::date_from_slice("2017-07-16").unwrap().to_string(), "2017-07-16");
+        assert_eq!(TrailerValue::list_from_slice("a,b", |i| Some(TrailerValue::from_slice(i)))
+                       .unwrap()
+                       .to_string(),
+                   "a, b");
+    }
+
     // Trailers tests
 
     #[test]
@@ -12,9 +12,16 @@
 
 use git2::Remote;
 
+use error::*;
+use error::ErrorKind as EK;
 use issue::Issue;
 
 
+/// Refspec for pushing all issues
+///
+pub const ALL_ISSUES_PUSH_REFSPEC: &'static str = "refs/dit/*:refs/dit/*";
+
+
 /// Extension trait for remotes
 ///
 pub trait RemoteExt {
@@ -29,6 +36,14 @@ pub trait RemoteExt {
     /// A refspec will only be returned if the remote has a (valid) name.
     ///
     fn all_issues_refspec(&self) -> Option<String>;
+
+    /// Check whether the remote is configured for dit
+    ///
+    /// A remote is considered configured for dit if its configuration contains
+    /// both the refspec for fetching all issues and the one for pushing them,
+    /// e.g. if plain fetches and pushes transfer issues.
+    ///
+    fn is_dit_configured(&self) -> Result<bool>;
 }
 
 impl<'r> RemoteExt for Remote<'r> {
@@ -41,5 +56,59 @@ impl<'r> RemoteExt for Remote<'r> {
         self.name()
             .map(|name| format!("+refs/dit/*:refs/remotes/{0}/dit/*", name))
     }
+
+    fn is_dit_configured(&self) -> Result<bool> {
+        let fetch_refspec = match self.all_issues_refspec() {
+            Some(refspec) => refspec,
+            None => return Ok(false),
+        };
+        let name = self.name().unwrap_or_default().to_owned();
+
+        let fetch = self
+            .fetch_refspecs()
+            .chain_err(|| EK::CannotGetRefspecs(name.clone()))?
+            .iter()
+            .any(|refspec| refspec == Some(fetch_refspec.as_str()));
+        let push = self
+            .push_refspecs()
+            .chain_err(|| EK::CannotGetRefspecs(name))?
+            .iter()
+            .any(|refspec| refspec == Some(ALL_ISSUES_PUSH_REFSPEC));
+        Ok(fetch && push)
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::TestingRepo;
+
+    // RemoteExt tests
+
+    #[test]
+    fn dit_configured() {
+        let mut testing_repo = TestingRepo::new("dit_configured");
+        let repo = testing_repo.repo();
+
+        repo.remote("origin", "https://example.com/repo.git")
+            .expect("Could not create remote");
+        let is_configured = || repo
+            .find_remote("origin")
+            .expect("Could not find remote")
+            .is_dit_configured()
+            .expect("Could not check remote");
+        assert!(!is_configured());
+
+        repo.remote_add_fetch("origin", "+refs/dit/*:refs/remotes/origin/dit/*")
+            .expect("Could not add refspec");
+        assert!(!is_configured());
+
+        repo.remote_add_push("origin", ALL_ISSUES_PUSH_REFSPEC)
+            .expect("Could not add refspec");
+        assert!(is_configured());
+    }
 }
 
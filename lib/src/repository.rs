@@ -14,11 +14,21 @@
 //!
 
 use git2::{self, Commit, Oid, Tree};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
 
 use issue::Issue;
 use error::*;
 use error::ErrorKind as EK;
 use iter;
+use mailmap::Mailmap;
+
+
+/// Name of the reference holding the repository-level configuration
+///
+pub const DIT_CONFIG_REF: &'static str = "refs/dit-config";
 
 
 /// Extension trait for Repositories
@@ -60,6 +70,15 @@ pub trait RepositoryExt {
     ///
     fn issues(&self) -> Result<iter::HeadRefsToIssuesIter>;
 
+    /// Get all issues, each one exactly once
+    ///
+    /// Unlike `issues()`, which yields an issue for each of its head references,
+    /// this function returns every issue known to the DIT repo only once, even
+    /// if it has a local head as well as remote heads. The issues are ordered
+    /// by id.
+    ///
+    fn distinct_issues(&self) -> Result<Vec<Issue>>;
+
     /// Create a new issue with an initial message
     ///
     fn create_issue<'a, A, I, J>(&self,
@@ -87,6 +106,32 @@ pub trait RepositoryExt {
     ///
     fn issue_messages_iter<'a>(&'a self, commit: Commit<'a>) -> Result<iter::IssueMessagesIter<'a>>;
 
+    /// Get a revwalk over a series of commits
+    ///
+    /// The revwalk returned will yield the commits reachable from `tip` but
+    /// not from `base`, starting with the oldest commit, e.g. in the order in
+    /// which the commits of a patch set would be applied.
+    ///
+    fn series_revwalk(&self, base: Oid, tip: Oid) -> Result<git2::Revwalk>;
+
+    /// Get a file from the repository-level configuration
+    ///
+    /// Configuration shared by all users of a repository, e.g. issue
+    /// templates, may be stored in the tree referenced by `refs/dit-config`.
+    /// This function returns the contents of the file at the path provided, if
+    /// present.
+    ///
+    fn dit_config_file(&self, path: &str) -> Result<Option<String>>;
+
+    /// Get the mailmap of the repository
+    ///
+    /// The mailmap is read from the `.mailmap` file in the working directory
+    /// or, for bare repositories, from `HEAD`, as well as from the file
+    /// configured via `mailmap.file`. If none of these exist, an empty mailmap
+    /// is returned.
+    ///
+    fn mailmap(&self) -> Result<Mailmap>;
+
     /// Get an empty tree
     ///
     /// This function returns an empty tree.
@@ -153,6 +198,15 @@ impl RepositoryExt for git2::Repository {
             .map(|refs| iter::HeadRefsToIssuesIter::new(self, refs))
     }
 
+    fn distinct_issues(&self) -> Result<Vec<Issue>> {
+        let mut issues = BTreeMap::new();
+        for issue in self.issues()? {
+            let issue = issue?;
+            issues.entry(issue.id()).or_insert(issue);
+        }
+        Ok(issues.into_iter().map(|(_, issue)| issue).collect())
+    }
+
     fn create_issue<'a, A, I, J>(&self,
              author: &git2::Signature,
              committer: &git2::Signature,
@@ -190,6 +244,71 @@ impl RepositoryExt for git2::Repository {
         iter::IssueMessagesIter::new(self, commit)
     }
 
+    fn series_revwalk(&self, base: Oid, tip: Oid) -> Result<git2::Revwalk> {
+        self.revwalk()
+            .and_then(|mut revwalk| {
+                revwalk.push(tip)?;
+                revwalk.hide(base)?;
+                revwalk.set_sorting(git2::SORT_TOPOLOGICAL | git2::SORT_REVERSE);
+                Ok(revwalk)
+            })
+            .chain_err(|| EK::CannotGetCommitForRev(format!("{}..{}", base, tip)))
+    }
+
+    fn dit_config_file(&self, path: &str) -> Result<Option<String>> {
+        let tree = match self.find_reference(DIT_CONFIG_REF) {
+            Ok(reference) => reference
+                .peel(git2::ObjectType::Tree)
+                .chain_err(|| EK::CannotGetReference)?,
+            Err(_) => return Ok(None), // no repository-level configuration
+        };
+
+        // the unwrap is safe since we peeled to a tree
+        let entry = match tree.as_tree().unwrap().get_path(Path::new(path)) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(None),
+        };
+
+        entry.to_object(self)
+            .and_then(|object| object.peel(git2::ObjectType::Blob))
+            .map(|object| object
+                .as_blob()
+                .map(|blob| String::from_utf8_lossy(blob.content()).into_owned())
+            )
+            .chain_err(|| EK::CannotReadConfigFile(path.to_owned()))
+    }
+
+    fn mailmap(&self) -> Result<Mailmap> {
+        let mut content = String::new();
+
+        let read_file = |path: &Path, content: &mut String| {
+            if let Ok(mut file) = File::open(path) {
+                file.read_to_string(content)
+                    .chain_err(|| EK::CannotReadConfigFile(path.to_string_lossy().into_owned()))?;
+                content.push('\n');
+            }
+            Ok(()) as Result<()>
+        };
+
+        match self.workdir() {
+            Some(workdir) => read_file(&workdir.join(".mailmap"), &mut content)?,
+            None => if let Ok(blob) = self
+                .revparse_single("HEAD:.mailmap")
+                .and_then(|object| object.peel(git2::ObjectType::Blob))
+            {
+                // the unwrap is safe since we peeled to a blob
+                content.push_str(&String::from_utf8_lossy(blob.as_blob().unwrap().content()));
+                content.push('\n');
+            },
+        }
+
+        if let Ok(path) = self.config().and_then(|config| config.get_path("mailmap.file")) {
+            read_file(&path, &mut content)?;
+        }
+
+        content.parse()
+    }
+
     fn empty_tree(&self) -> Result<Tree> {
         self.treebuilder(None)
             .and_then(|treebuilder| treebuilder.write())
@@ -300,6 +419,30 @@ mod tests {
         assert!(issues.next().is_none());
     }
 
+    #[test]
+    fn distinct_issues() {
+        let mut testing_repo = TestingRepo::new("distinct_issues");
+        let repo = testing_repo.repo();
+
+        let sig = git2::Signature::now("Foo Bar", "foo.bar@example.com")
+            .expect("Could not create signature");
+        let empty_tree = repo
+            .empty_tree()
+            .expect("Could not create empty tree");
+        let issue = repo
+            .create_issue(&sig, &sig, "Test message 1", &empty_tree, vec![])
+            .expect("Could not create issue");
+        repo.reference(&format!("refs/remotes/origin/dit/{}/head", issue.ref_part()), issue.id(), false, "Remote head")
+            .expect("Could not create remote head");
+
+        assert_eq!(repo.issues().expect("Could not retrieve issues").count(), 2);
+        let issues = repo
+            .distinct_issues()
+            .expect("Could not retrieve issues");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].id(), issue.id());
+    }
+
     #[test]
     fn first_parent_revwalk() {
         let mut testing_repo = TestingRepo::new("first_parent_revwalk");
@@ -370,5 +513,66 @@ mod tests {
         assert_eq!(iter2.next().unwrap().unwrap().id(), issue2.id());
         assert!(iter2.next().is_none());
     }
-}
 
+    #[test]
+    fn dit_config_file() {
+        let mut testing_repo = TestingRepo::new("dit_config_file");
+        let repo = testing_repo.repo();
+
+        assert!(repo.dit_config_file("templates/bug").unwrap().is_none());
+
+        let blob = repo
+            .blob(b"Bug report\n\nDit-type: bug\n")
+            .expect("Could not create blob");
+        let subtree = repo
+            .treebuilder(None)
+            .and_then(|mut builder| {
+                builder.insert("bug", blob, 0o100644)?;
+                builder.write()
+            })
+            .expect("Could not create tree");
+        let tree = repo
+            .treebuilder(None)
+            .and_then(|mut builder| {
+                builder.insert("templates", subtree, 0o040000)?;
+                builder.write()
+            })
+            .expect("Could not create tree");
+        repo.reference(DIT_CONFIG_REF, tree, false, "test")
+            .expect("Could not create reference");
+
+        assert_eq!(
+            repo.dit_config_file("templates/bug").unwrap(),
+            Some(String::from("Bug report\n\nDit-type: bug\n"))
+        );
+        assert!(repo.dit_config_file("templates/feature").unwrap().is_none());
+    }
+
+    #[test]
+    fn mailmap() {
+        let mut testing_repo = TestingRepo::new("mailmap");
+        let repo = testing_repo.repo();
+
+        assert_eq!(repo.mailmap().unwrap().canonical("Foo", "foo@example.com"),
+                   (String::from("Foo"), String::from("foo@example.com")));
+
+        let sig = git2::Signature::now("Foo Bar", "foo.bar@example.com")
+            .expect("Could not create signature");
+        let blob = repo
+            .blob(b"Foo Bar <foo.bar@example.com> <foo@example.com>\n")
+            .expect("Could not create blob");
+        let tree = repo
+            .treebuilder(None)
+            .and_then(|mut builder| {
+                builder.insert(".mailmap", blob, 0o100644)?;
+                builder.write()
+            })
+            .and_then(|id| repo.find_tree(id))
+            .expect("Could not create tree");
+        repo.commit(Some("HEAD"), &sig, &sig, "Add mailmap", &tree, &[])
+            .expect("Could not create commit");
+
+        assert_eq!(repo.mailmap().unwrap().canonical("Foo", "foo@example.com"),
+                   (String::from("Foo Bar"), String::from("foo.bar@example.com")));
+    }
+}
@@ -0,0 +1,137 @@
+// git-dit - the distributed issue tracker for git
+// Copyright (C) 2017 Matthias Beyer <mail@beyermatthias.de>
+// Copyright (C) 2017 Julian Ganz <neither@nut.email>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Full-text search
+//!
+//! This module provides the `Query` type for finding messages by their text.
+//! A query is matched against the subject, the lines of text in the body and
+//! the values of the trailers of a message, line by line.
+//!
+
+use regex::{self, Regex, RegexBuilder};
+
+use error::*;
+use error::ErrorKind as EK;
+use message::LineIteratorExt;
+use message::line::Line;
+
+
+/// Full-text query
+///
+#[derive(Clone, Debug)]
+pub struct Query(Regex);
+
+impl Query {
+    /// Create a query matching a regular expression
+    ///
+    pub fn regex(pattern: &str, ignore_case: bool) -> Result<Self> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map(Query)
+            .chain_err(|| EK::MalformedQuery(pattern.to_owned()))
+    }
+
+    /// Create a query matching a literal string
+    ///
+    pub fn literal(pattern: &str, ignore_case: bool) -> Result<Self> {
+        Self::regex(&regex::escape(pattern), ignore_case)
+    }
+
+    /// Check whether a piece of text matches the query
+    ///
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+
+    /// Get the lines of a message matching the query
+    ///
+    /// The message is supplied as a sequence of lines, the first one being the
+    /// subject. Trailers are only matched by their value but returned in their
+    /// entirety.
+    ///
+    pub fn matching_lines<I, S>(&self, lines: I) -> Vec<String>
+        where I: IntoIterator<Item = S>,
+              S: AsRef<str>
+    {
+        let mut lines = lines.into_iter();
+        let mut retval : Vec<String> = lines
+            .next()
+            .into_iter()
+            .filter(|subject| self.is_match(subject.as_ref()))
+            .map(|subject| subject.as_ref().to_owned())
+            .collect();
+
+        for line in lines.categorized_lines() {
+            match line {
+                Line::Text(text) => if self.is_match(&text) {
+                    retval.push(text);
+                },
+                Line::Trailer(trailer) => if self.is_match(&trailer.value.to_string()) {
+                    retval.push(trailer.to_string());
+                },
+                Line::Blank => {},
+            }
+        }
+
+        retval
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &'static [&'static str] = &[
+        "Crash on startup",
+        "",
+        "The program crashes (sometimes) when started.",
+        "Restarting helps.",
+        "",
+        "Dit-status: crashed",
+    ];
+
+    // Query tests
+
+    #[test]
+    fn literal_query() {
+        let query = Query::literal("(sometimes)", false).expect("Could not create query");
+        assert_eq!(query.matching_lines(MESSAGE), vec!["The program crashes (sometimes) when started."]);
+
+        let query = Query::literal("crash", false).expect("Could not create query");
+        assert_eq!(query.matching_lines(MESSAGE), vec![
+            "The program crashes (sometimes) when started.",
+            "Dit-status: crashed",
+        ]);
+
+        let query = Query::literal("crash", true).expect("Could not create query");
+        assert_eq!(query.matching_lines(MESSAGE), vec![
+            "Crash on startup",
+            "The program crashes (sometimes) when started.",
+            "Dit-status: crashed",
+        ]);
+
+        let query = Query::literal("Dit-status", false).expect("Could not create query");
+        assert!(query.matching_lines(MESSAGE).is_empty());
+    }
+
+    #[test]
+    fn regex_query() {
+        let query = Query::regex("^Rest.*helps", false).expect("Could not create query");
+        assert_eq!(query.matching_lines(MESSAGE), vec!["Restarting helps."]);
+
+        let query = Query::regex("^crash", true).expect("Could not create query");
+        assert_eq!(query.matching_lines(MESSAGE), vec!["Crash on startup", "Dit-status: crashed"]);
+
+        assert!(Query::regex("(unbalanced", false).is_err());
+    }
+}
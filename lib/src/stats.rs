@@ -0,0 +1,263 @@
+// git-dit - the distributed issue tracker for git
+// Copyright (C) 2017 Matthias Beyer <mail@beyermatthias.de>
+// Copyright (C) 2017 Julian Ganz <neither@nut.email>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Issue statistics
+//!
+//! This module provides functionality for deriving statistics from issues. The
+//! `Lifecycle` of an issue captures the points in time at which it was created,
+//! first replied to and changed in status, derived from the commit times and
+//! `Dit-status` trailers of its messages. Times are given in seconds since the
+//! epoch, durations in seconds.
+//!
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use git2::Commit;
+use std::i64;
+use std::str::FromStr;
+
+use error::*;
+use error::ErrorKind as EK;
+use issue::{Issue, MetadataWalk};
+use message::Message;
+use message::trailer;
+
+
+/// Lifecycle of an issue
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lifecycle {
+    /// Time at which the issue was created
+    pub created: i64,
+    /// Time of the earliest reply, if any
+    pub first_reply: Option<i64>,
+    /// Changes of the status, in chronological order
+    pub transitions: Vec<(i64, String)>,
+}
+
+impl Lifecycle {
+    /// Determine the lifecycle of an issue
+    ///
+    /// The first reply is determined from all messages of the issue. The
+    /// transitions are derived from the messages selected by the walk supplied,
+    /// e.g. the messages from which the metadata of the issue is accumulated.
+    ///
+    pub fn of(issue: &Issue, walk: &MetadataWalk) -> Result<Self> {
+        issue.metadata_messages(walk).and_then(|messages| Self::from_messages(issue, messages))
+    }
+
+    /// Determine the lifecycle of an issue from a selection of its messages
+    ///
+    /// The transitions are derived from the messages supplied, which are
+    /// expected in reverse order as returned by `Issue::metadata_messages()`.
+    ///
+    pub fn from_messages<'r, I>(issue: &Issue, messages: I) -> Result<Self>
+        where I: IntoIterator<Item = Commit<'r>>
+    {
+        let created = issue.initial_message()?.time().seconds();
+
+        let first_reply = issue
+            .messages_since(i64::MIN)?
+            .iter()
+            .filter(|message| message.id() != issue.id())
+            .map(|message| message.time().seconds())
+            .min();
+
+        let mut messages : Vec<Commit> = messages.into_iter().collect();
+        messages.reverse();
+        let transitions = messages
+            .iter()
+            .flat_map(|message| {
+                let time = message.time().seconds();
+                message
+                    .trailers()
+                    .filter(|t| t.key.as_ref() == trailer::STATUS_KEY)
+                    .map(move |t| (time, t.value.to_string()))
+            })
+            .collect();
+
+        Ok(Lifecycle { created: created, first_reply: first_reply, transitions: transitions })
+    }
+
+    /// Get the time at which the issue was closed
+    ///
+    /// If the latest status is one of the closed states supplied, the time of
+    /// the transition from the last state not considered closed is returned.
+    ///
+    pub fn closed(&self, closed: &[String]) -> Option<i64> {
+        let is_closed = |status: &String| closed.iter().any(|c| c == status);
+        if !self.transitions.last().map(|t| is_closed(&t.1)).unwrap_or(false) {
+            return None;
+        }
+
+        self.transitions
+            .iter()
+            .rev()
+            .take_while(|t| is_closed(&t.1))
+            .last()
+            .map(|t| t.0)
+    }
+
+    /// Get the time it took to close the issue
+    ///
+    pub fn time_to_close(&self, closed: &[String]) -> Option<i64> {
+        self.closed(closed).map(|time| time - self.created)
+    }
+
+    /// Get the time it took for the first reply to arrive
+    ///
+    pub fn time_to_first_reply(&self) -> Option<i64> {
+        self.first_reply.map(|time| time - self.created)
+    }
+}
+
+
+/// Period by which points in time may be grouped
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Period {
+    Day,
+    /// Weeks starting on monday
+    Week,
+    Month,
+}
+
+impl Period {
+    /// Get the first day of the period containing a point in time
+    ///
+    /// The point in time is interpreted as UTC.
+    ///
+    pub fn start(&self, time: i64) -> NaiveDate {
+        let date = NaiveDateTime::from_timestamp(time, 0).date();
+        match *self {
+            Period::Day     => date,
+            Period::Week    => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Month   => NaiveDate::from_ymd(date.year(), date.month(), 1),
+        }
+    }
+}
+
+impl FromStr for Period {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "day"   => Ok(Period::Day),
+            "week"  => Ok(Period::Week),
+            "month" => Ok(Period::Month),
+            _ => Err(Error::from_kind(EK::UnknownPeriod(s.to_owned()))),
+        }
+    }
+}
+
+
+/// Compute the median of a sequence of values
+///
+/// For an even number of values, the lower one of the two middle values is
+/// returned.
+///
+pub fn median<I>(values: I) -> Option<i64>
+    where I: IntoIterator<Item = i64>
+{
+    let mut values : Vec<i64> = values.into_iter().collect();
+    values.sort();
+    match values.len() {
+        0 => None,
+        n => Some(values[(n - 1) / 2]),
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2;
+    use test_utils::TestingRepo;
+    use repository::RepositoryExt;
+
+    // Lifecycle tests
+
+    #[test]
+    fn lifecycle() {
+        let mut testing_repo = TestingRepo::new("lifecycle");
+        let repo = testing_repo.repo();
+
+        let sig = |time| git2::Signature::new("Foo Bar", "foo.bar@example.com", &git2::Time::new(time, 0))
+            .expect("Could not create signature");
+        let empty_tree = repo
+            .empty_tree()
+            .expect("Could not create empty tree");
+
+        let issue = repo
+            .create_issue(&sig(1000), &sig(1000), "Test message 1", &empty_tree, vec![])
+            .expect("Could not create issue");
+        let initial_message = issue
+            .initial_message()
+            .expect("Could not retrieve initial message");
+        let reply = issue
+            .add_message(&sig(1500), &sig(1500), "Test message 2\n\nDit-status: closed", &empty_tree, vec![&initial_message])
+            .expect("Could not add message");
+        let reply = issue
+            .add_message(&sig(2000), &sig(2000), "Test message 3\n\nDit-status: open", &empty_tree, vec![&reply])
+            .expect("Could not add message");
+        issue
+            .update_head(reply.id())
+            .expect("Could not update head");
+
+        // messages off the first-parent chain of the head are not considered
+        issue
+            .add_message(&sig(2500), &sig(2500), "Test message 6\n\nDit-status: closed", &empty_tree, vec![&initial_message])
+            .expect("Could not add message");
+
+        let walk = MetadataWalk::default();
+        let closed = vec![String::from("closed"), String::from("wontfix")];
+        let lifecycle = Lifecycle::of(&issue, &walk).expect("Could not determine lifecycle");
+        assert_eq!(lifecycle.created, 1000);
+        assert_eq!(lifecycle.time_to_first_reply(), Some(500));
+        assert_eq!(lifecycle.closed(&closed), None);
+
+        let reply = issue
+            .add_message(&sig(3000), &sig(3000), "Test message 4\n\nDit-status: closed", &empty_tree, vec![&reply])
+            .expect("Could not add message");
+        let reply = issue
+            .add_message(&sig(4000), &sig(4000), "Test message 5\n\nDit-status: wontfix", &empty_tree, vec![&reply])
+            .expect("Could not add message");
+        issue
+            .update_head(reply.id())
+            .expect("Could not update head");
+
+        let lifecycle = Lifecycle::of(&issue, &walk).expect("Could not determine lifecycle");
+        assert_eq!(lifecycle.transitions.len(), 4);
+        assert_eq!(lifecycle.closed(&closed), Some(3000));
+        assert_eq!(lifecycle.time_to_close(&closed), Some(2000));
+    }
+
+    // Period tests
+
+    #[test]
+    fn period_start() {
+        // 2017-07-13T12:00:00Z, a thursday
+        let time = 1499947200;
+        assert_eq!(Period::Day.start(time), NaiveDate::from_ymd(2017, 7, 13));
+        assert_eq!(Period::Week.start(time), NaiveDate::from_ymd(2017, 7, 10));
+        assert_eq!(Period::Month.start(time), NaiveDate::from_ymd(2017, 7, 1));
+        assert!(Period::from_str("year").is_err());
+    }
+
+    // Median tests
+
+    #[test]
+    fn median_values() {
+        assert_eq!(median(vec![]), None);
+        assert_eq!(median(vec![5]), Some(5));
+        assert_eq!(median(vec![7, 1, 5]), Some(5));
+        assert_eq!(median(vec![7, 1, 5, 3]), Some(3));
+    }
+}
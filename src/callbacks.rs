@@ -8,11 +8,13 @@
 //
 
 use git2::{self, Cred};
+use std::cell::RefCell;
 use std::io::{self, Write};
 use std::result::Result as RResult;
 use std::str;
 
 use logger::LoggableError;
+use summary::RefUpdate;
 
 
 /// Get credentials from the user
@@ -79,3 +81,38 @@ pub fn callbacks() -> git2::RemoteCallbacks<'static> {
     retval
 }
 
+
+
+/// Reference updates collected during a fetch or push
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Updates {
+    /// Updated tips
+    pub tips: Vec<RefUpdate>,
+    /// References rejected by the remote, along with the reason
+    pub rejected: Vec<(String, String)>,
+}
+
+
+/// Callbacks collecting reference updates instead of printing them
+///
+pub fn collecting_callbacks<'a>(updates: &'a RefCell<Updates>) -> git2::RemoteCallbacks<'a> {
+    let mut retval = git2::RemoteCallbacks::new();
+    retval.credentials(get_creds);
+    retval.sideband_progress(print_sideband);
+    retval.update_tips(move |refname, old, new| {
+        updates.borrow_mut().tips.push(RefUpdate {
+            name: refname.to_owned(),
+            old: if old.is_zero() { None } else { Some(old) },
+            new: if new.is_zero() { None } else { Some(new) },
+        });
+        true
+    });
+    retval.push_update_reference(move |refname, failmsg| {
+        if let Some(msg) = failmsg {
+            updates.borrow_mut().rejected.push((refname.to_owned(), msg.to_owned()));
+        }
+        Ok(())
+    });
+    retval
+}
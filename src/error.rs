@@ -19,6 +19,11 @@ error_chain! {
             display("Cannot open repository")
         }
 
+        CannotOpenBugRepository(location: String) {
+            description("Cannot open bug repository")
+            display("Cannot open bug repository: {}", location)
+        }
+
         CannotGetRepositoryConfig {
             description("Cannot get repository config")
             display("Cannot get repository config")
@@ -34,6 +39,36 @@ error_chain! {
             display("Missing parameter: {}", name)
         }
 
+        MalformedRange(range: String) {
+            description("Malformed range")
+            display("Malformed range: {}", range)
+        }
+
+        MalformedDate(date: String) {
+            description("Malformed date")
+            display("Malformed date: {}", date)
+        }
+
+        MalformedSortKey(key: String) {
+            description("Malformed sort key")
+            display("Malformed sort key: {}", key)
+        }
+
+        UnknownTemplate(name: String) {
+            description("Unknown template")
+            display("Unknown template: {}", name)
+        }
+
+        UnknownRepository(name: String) {
+            description("Unknown aggregated repository")
+            display("Unknown aggregated repository: {}", name)
+        }
+
+        UnknownPerson(person: String) {
+            description("Unknown person")
+            display("Cannot resolve person: {}", person)
+        }
+
         WrappedIOError {
             description("IO Error")
             display("IO Error")
@@ -21,18 +21,27 @@ mod error;
 mod logger;
 mod msgtree;
 mod programs;
+mod report;
+mod sort;
+mod summary;
 mod util;
 mod write;
 
-use chrono::{FixedOffset, TimeZone};
+use chrono::{FixedOffset, NaiveDate, TimeZone, UTC};
 use clap::App;
 use git2::{Commit, Repository};
-use libgitdit::issue::IssueRefType;
-use libgitdit::message::accumulation::{self, Accumulator};
-use libgitdit::message::trailer::PairsToTrailers;
+use libgitdit::index::IndexQuery;
+use libgitdit::issue::{IssueRefType, MessageOrder, MetadataWalk, Trust};
+use libgitdit::message::accumulation::{self, Accumulator, ValueAccumulator};
+use libgitdit::message::line::Line;
+use libgitdit::message::trailer::{self, PairsToTrailers, TrailerValue};
 use libgitdit::message::{LineIteratorExt, Trailer};
-use libgitdit::{Message, RemoteExt, RepositoryExt};
+use libgitdit::search::Query;
+use libgitdit::stats::{Lifecycle, Period, median};
+use libgitdit::remote::ALL_ISSUES_PUSH_REFSPEC;
+use libgitdit::{Issue, Message, RemoteExt, RepositoryExt};
 use log::LogLevel;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::{self, Read, Write};
 use std::process::Command;
@@ -42,7 +51,11 @@ use abort::{Abortable, IteratorExt};
 use error::*;
 use error::ErrorKind as EK;
 use msgtree::{IntoTreeGraph, TreeGraphElem, TreeGraphElemLine};
-use util::{RepositoryUtil, message_from_args};
+use report::{Format, Report, Table, Value};
+use sort::SortKey;
+use summary::{RefUpdate, Summary};
+use util::{RepositoryUtil, carries_trailer, duplicate_of, is_open, message_from_args, metadata_trailers, metadata_value,
+           metadata_values, qualified_issue, unread_count, verify_signatures};
 use write::WriteExt;
 
 
@@ -50,19 +63,22 @@ use write::WriteExt;
 
 /// check-message subcommand implementation
 ///
-fn check_message(matches: &clap::ArgMatches) {
+fn check_message(repo: &Repository, matches: &clap::ArgMatches) {
     let reader: Box<Read> = match matches.value_of("filename") {
         Some(filename)  => Box::from(File::open(filename).unwrap_or_abort()),
         None            => Box::from(io::stdin()),
     };
     use io::BufRead;
-    io::BufReader::new(reader)
+    let lines : Vec<String> = io::BufReader::new(reader)
         .lines()
         .abort_on_err()
         .skip_while(|l| l.is_empty())
         .stripped()
+        .collect();
+    lines.iter()
         .check_message_format()
         .unwrap_or_abort();
+    repo.validate_trailers(lines.iter()).unwrap_or_abort();
 }
 
 
@@ -128,18 +144,50 @@ fn get_issue_metadata(repo: &Repository, matches: &clap::ArgMatches) {
     let head = repo
         .value_to_commit(matches.value_of("head").unwrap())
         .unwrap_or_abort();
-    let trailers = repo
-        .issue_messages_iter(head)
-        .abort_on_err()
-        .flat_map(|commit| commit.trailers());
+    let schema = repo.metadata_schema().unwrap_or_abort();
+
+    // determine the messages from which to extract the metadata
+    let mut walk = repo.metadata_walk().unwrap_or_abort();
+    if let Some(order) = matches.value_of("order") {
+        walk.order = order.parse().unwrap_or_abort();
+    }
+    if let Some(trust) = matches.value_of("trust") {
+        let config = repo.config().unwrap_or_abort();
+        walk.trust = Trust::from_name(trust, &config).unwrap_or_abort();
+    }
+    let messages = if walk.order == MessageOrder::FirstParent {
+        let messages = repo
+            .issue_messages_iter(head)
+            .abort_on_err()
+            .filter(|message| walk.trust.trusts(message))
+            .collect();
+        if walk.trust == Trust::Signed {
+            verify_signatures(repo, messages).unwrap_or_abort()
+        } else {
+            messages
+        }
+    } else {
+        let issue = repo.issue_with_message(&head).unwrap_or_abort();
+        repo.metadata_messages(&issue, &walk).unwrap_or_abort()
+    };
+    let trailers = messages
+        .into_iter()
+        .flat_map(|commit| commit.trailers())
+        .map(|trailer| schema.typed(trailer));
 
     if let Some(key) = matches.value_of("key") {
         let policy = if matches.is_present("accumulate-latest") {
             accumulation::AccumulationPolicy::Latest
         } else if matches.is_present("accumulate-list") {
             accumulation::AccumulationPolicy::List
+        } else if matches.is_present("accumulate-first") {
+            accumulation::AccumulationPolicy::First
+        } else if matches.is_present("accumulate-union") {
+            accumulation::AccumulationPolicy::Union
+        } else if matches.is_present("accumulate-count") {
+            accumulation::AccumulationPolicy::Count
         } else {
-            accumulation::AccumulationPolicy::List
+            schema.policy(key)
         };
         let mut acc = accumulation::SingleAccumulator::new(key.to_owned(), policy);
         acc.process_all(trailers);
@@ -163,6 +211,100 @@ fn get_issue_tree_init_hashes(repo: &Repository, _: &clap::ArgMatches) {
 
 // Porcelain subcommand implementations
 
+/// assign subcommand implementation
+///
+fn assign_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let issue = repo.cli_issue(matches).unwrap_or_abort();
+    let person = repo.resolve_person(matches.value_of("person")).unwrap_or_abort();
+
+    let metadata = repo.issue_metadata(&issue).unwrap_or_abort();
+    if metadata_value(&metadata, trailer::ASSIGNEE_KEY) == Some(&person) {
+        warn!("No commit was created because {} is already assigned to the issue.", person);
+        return;
+    }
+
+    let trailer = Trailer { key: String::from(trailer::ASSIGNEE_KEY).into(), value: person };
+    repo.metadata_schema().unwrap_or_abort().validate(&trailer).unwrap_or_abort();
+    repo.tag_issue(&issue, &[trailer], &[]).unwrap_or_abort();
+}
+
+
+/// checkout subcommand implementation
+///
+fn checkout_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let issue = repo.cli_issue(matches).unwrap_or_abort();
+    if issue.patch_set_base().unwrap_or_abort().is_none() {
+        error!("Issue {} is not a patch set", issue);
+        std::process::exit(1);
+    }
+
+    // the tip of the series is the first parent of the initial message
+    let tip = issue
+        .initial_message()
+        .unwrap_or_abort()
+        .parent(0)
+        .unwrap_or_abort();
+
+    let name = matches
+        .value_of("branch")
+        .map(String::from)
+        .unwrap_or_else(|| format!("dit/{}", issue));
+    let branch = repo
+        .branch(name.as_ref(), &tip, matches.is_present("force"))
+        .unwrap_or_abort();
+
+    if !matches.is_present("no-checkout") {
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.safe();
+        repo.checkout_tree(tip.as_object(), Some(&mut checkout))
+            .unwrap_or_abort();
+
+        // the unwrap is safe since we just created the branch with a valid name
+        repo.set_head(branch.get().name().unwrap())
+            .unwrap_or_abort();
+    }
+
+    println!("[dit][checkout] {}", name);
+}
+
+/// duplicate subcommand implementation
+///
+fn duplicate_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let issue = repo.cli_issue(matches).unwrap_or_abort();
+    // note: "original" is always present since it is a required parameter
+    let original = repo.value_to_issue(matches.value_of("original").unwrap()).unwrap_or_abort();
+    if original.id() == issue.id() {
+        error!("Issue {} cannot be a duplicate of itself", issue);
+        std::process::exit(1);
+    }
+
+    let metadata = repo.issue_metadata(&issue).unwrap_or_abort();
+    if duplicate_of(&metadata) == Some(original.id()) {
+        warn!("No commit was created because the issue is already marked as a duplicate of {}.", original);
+        return;
+    }
+
+    // the duplicate is closed using the first of the states considered closed
+    let status = repo
+        .closed_states()
+        .unwrap_or_abort()
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| String::from("closed"));
+    let trailers = [
+        Trailer::new(trailer::DUPLICATE_KEY, &original.id().to_string()),
+        Trailer::new(trailer::STATUS_KEY, &status),
+    ];
+    let schema = repo.metadata_schema().unwrap_or_abort();
+    for trailer in trailers.iter() {
+        schema.validate(trailer).unwrap_or_abort();
+    }
+
+    let reference = repo.find_commit(original.id()).unwrap_or_abort();
+    repo.tag_issue(&issue, &trailers, &[reference]).unwrap_or_abort();
+}
+
+
 /// fetch subcommand implementation
 ///
 fn fetch_impl(repo: &Repository, matches: &clap::ArgMatches) {
@@ -187,37 +329,92 @@ fn fetch_impl(repo: &Repository, matches: &clap::ArgMatches) {
         vec![remote.all_issues_refspec().unwrap()]
     };
 
-    // set the options for the fetch
-    let mut fetch_options = git2::FetchOptions::new();
-    fetch_options.prune(if matches.is_present("prune") {
-        git2::FetchPrune::On
-    } else {
-        git2::FetchPrune::Unspecified
-    });
-    fetch_options.remote_callbacks(callbacks::callbacks());
-
     let refspec_refs : Vec<&str> = refspecs.iter().map(String::as_str).collect();
-    remote.fetch(refspec_refs.as_ref(), Some(&mut fetch_options), None)
-          .unwrap_or_abort();
+    let summary = summary::fetch(repo, &mut remote, refspec_refs.as_ref(), matches.is_present("prune"))
+        .unwrap_or_abort();
+    print_summary(repo, &summary, "fetch", &remote, matches);
 }
 
 
 /// list subcommand implementation
 ///
 fn list_impl(repo: &Repository, matches: &clap::ArgMatches) {
-    // get initial commits
-    let mut commits : Vec<Commit> = repo.issues()
-        .abort_on_err()
-        .map(|issue| repo.find_commit(issue.id()))
-        .abort_on_err()
-        .collect();
+    let labels : Vec<&str> = matches
+        .values_of("label")
+        .map(|labels| labels.collect())
+        .unwrap_or_default();
+
+    let keys : Vec<SortKey> = {
+        let config = repo.config().unwrap_or_abort();
+        matches
+            .values_of("sort")
+            .map(|specs| specs.map(|spec| SortKey::from_spec(spec, &config)).abort_on_err().collect())
+            .unwrap_or_else(|| vec![SortKey::default()])
+    };
+    let show_duplicates = matches.is_present("duplicates");
+    let needs_metadata = !labels.is_empty() || !show_duplicates || keys.iter().any(SortKey::needs_metadata);
+
+    // issues of aggregated repositories are listed along with our own
+    let aggregated = repo.aggregated_repositories().unwrap_or_abort();
+    let sources = Some((None, repo))
+        .into_iter()
+        .chain(aggregated.iter().map(|&(ref name, ref repo)| (Some(name.as_str()), repo)));
+
+    // get initial commits along with the values to sort by
+    let mut issues : Vec<(Vec<Option<TrailerValue>>, Option<&str>, Commit, Option<usize>)> = Vec::new();
+    for (source, repo) in sources {
+        // unread messages are only counted if requested
+        let seen = if matches.is_present("unread") {
+            Some(repo.seen_messages().unwrap_or_abort())
+        } else {
+            None
+        };
+
+        for issue in repo.issues().abort_on_err() {
+            let unread = match seen {
+                Some(ref seen) => match unread_count(&issue, seen).unwrap_or_abort() {
+                    0 => continue,
+                    count => Some(count),
+                },
+                None => None,
+            };
+
+            let mut metadata = if needs_metadata {
+                repo.issue_metadata(&issue).unwrap_or_abort()
+            } else {
+                BTreeMap::new()
+            };
+
+            if !show_duplicates && duplicate_of(&metadata).is_some() {
+                continue;
+            }
 
-    // descending order, maybe limited to some number specified by the user
-    commits.sort_by(|a, b| b.time().cmp(&a.time()));
+            if !labels.is_empty() {
+                let present : Vec<String> = metadata_values(&mut metadata, trailer::LABEL_KEY)
+                    .into_iter()
+                    .map(|label| label.to_string())
+                    .collect();
+                if !labels.iter().all(|label| present.iter().any(|p| p == label)) {
+                    continue;
+                }
+            }
+
+            let values = keys
+                .iter()
+                .map(|key| key.value(&issue, &metadata))
+                .abort_on_err()
+                .collect();
+            issues.push((values, source, repo.find_commit(issue.id()).unwrap_or_abort(), unread));
+        }
+    }
+
+    // sort, maybe limited to some number specified by the user
+    issues.sort_by(|a, b| sort::compare(&keys, &a.0, &b.0));
     if let Some(number) = matches.value_of("n") {
         // TODO: better error reporting?
-        commits.truncate(str::parse(number).unwrap_or_abort());
+        issues.truncate(str::parse(number).unwrap_or_abort());
     }
+    let commits = issues.into_iter().map(|(_, source, commit, unread)| (source, commit, unread));
 
     let id_len = repo.abbreviation_length(matches).unwrap_or_abort();
 
@@ -228,19 +425,31 @@ fn list_impl(repo: &Repository, matches: &clap::ArgMatches) {
     {
         let mut stream = pager.stdin.as_mut().unwrap();
         let long = matches.is_present("long");
-        for mut commit in commits {
-            let id = commit.id();
+        for (source, mut commit, unread) in commits {
+            let mut id = commit.id().to_string();
             let time = {
                 let gtime = commit.time();
                 FixedOffset::east(gtime.offset_minutes()*60).timestamp(gtime.seconds(), 0)
             };
             if long {
-                write!(stream, "Issue:  {}\nAuthor: {}\nDate:   {}\n\n", id, commit.author(), time.to_rfc3339())
+                if let Some(source) = source {
+                    id = format!("{}:{}", source, id);
+                }
+                write!(stream, "Issue:  {}\nAuthor: {}\nDate:   {}\n", id, commit.author(), time.to_rfc3339())
                     .unwrap_or_abort();
+                if let Some(unread) = unread {
+                    write!(stream, "Unread: {}\n", unread).unwrap_or_abort();
+                }
+                write!(stream, "\n").unwrap_or_abort();
                 stream.consume_lines(commit.message_lines()).unwrap_or_abort();
                 write!(stream, "\n\n").unwrap_or_abort();
             } else {
-                writeln!(stream, "{0:.1$} ({2}) {3}", id, id_len, time.format("%c"), commit.summary().unwrap_or(""))
+                id.truncate(id_len);
+                if let Some(source) = source {
+                    id = format!("{}:{}", source, id);
+                }
+                let unread = unread.map(|count| format!(" ({} unread)", count)).unwrap_or_default();
+                writeln!(stream, "{} ({}) {}{}", id, time.format("%c"), commit.summary().unwrap_or(""), unread)
                     .unwrap_or_abort();
             }
         }
@@ -254,15 +463,300 @@ fn list_impl(repo: &Repository, matches: &clap::ArgMatches) {
 }
 
 
+/// label subcommand implementation
+///
+fn label_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let issue = repo.cli_issue(matches).unwrap_or_abort();
+    let mut metadata = repo.issue_metadata(&issue).unwrap_or_abort();
+    let current : BTreeSet<String> = metadata_values(&mut metadata, trailer::LABEL_KEY)
+        .into_iter()
+        .map(|label| label.to_string())
+        .collect();
+
+    // note: "action" is always present since it is a required parameter
+    let (prefix, add) = match matches.value_of("action").unwrap() {
+        "add"       => ("+", true),
+        "remove"    => ("-", false),
+        _ => {
+            io::stdout().consume_lines(current).unwrap_or_abort();
+            return;
+        },
+    };
+
+    // we only record changes to the set of labels
+    let schema = repo.metadata_schema().unwrap_or_abort();
+    let trailers : Vec<Trailer> = matches
+        .values_of("label")
+        .into_iter()
+        .flat_map(|labels| labels)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|label| current.contains(*label) != add)
+        .map(|label| Trailer::new(trailer::LABEL_KEY, &format!("{}{}", prefix, label)))
+        .inspect(|t| schema.validate(t).unwrap_or_abort())
+        .collect();
+    if trailers.is_empty() {
+        warn!("No commit was created because the labels were not changed.");
+        return;
+    }
+
+    repo.tag_issue(&issue, &trailers, &[]).unwrap_or_abort();
+}
+
+
+/// labels subcommand implementation
+///
+fn labels_impl(repo: &Repository, _: &clap::ArgMatches) {
+    // count the issues carrying each label
+    let mut counts : BTreeMap<String, usize> = BTreeMap::new();
+    for issue in repo.distinct_issues().unwrap_or_abort() {
+        let mut metadata = repo.issue_metadata(&issue).unwrap_or_abort();
+        for label in metadata_values(&mut metadata, trailer::LABEL_KEY) {
+            *counts.entry(label.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    // most used labels first
+    let mut counts : Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    io::stdout()
+        .consume_lines(counts.into_iter().map(|(label, count)| format!("{:>6} {}", count, label)))
+        .unwrap_or_abort();
+}
+
+
+/// lint subcommand implementation
+///
+fn lint_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let schema = repo.metadata_schema().unwrap_or_abort();
+
+    let issues : Vec<_> = if let Some(issues) = matches.values_of("issue") {
+        issues.map(|issue| repo.value_to_issue(issue)).abort_on_err().collect()
+    } else {
+        repo.issues().abort_on_err().collect()
+    };
+
+    // check the metadata of every message of every issue
+    let mut valid = true;
+    for issue in issues {
+        let messages = issue
+            .message_revwalk()
+            .abort_on_err()
+            .map(|oid| repo.find_commit(oid))
+            .abort_on_err();
+        for message in messages {
+            for trailer in message.trailers() {
+                if let Err(err) = schema.validate(&trailer) {
+                    println!("{} {}: {}", issue, message.id(), err);
+                    valid = false;
+                }
+            }
+        }
+    }
+
+    if !valid {
+        std::process::exit(1);
+    }
+}
+
+
+/// log subcommand implementation
+///
+fn log_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let issue = repo.cli_issue(matches).unwrap_or_abort();
+    let schema = repo.metadata_schema().unwrap_or_abort();
+    let id_len = repo.abbreviation_length(matches).unwrap_or_abort();
+
+    // the messages on the first-parent chain of the head, oldest first
+    let mut messages = issue.metadata_messages(&MetadataWalk::default()).unwrap_or_abort();
+    messages.reverse();
+
+    let display = |accumulator: Option<&ValueAccumulator>| -> String {
+        let values : Vec<String> = accumulator
+            .into_iter()
+            .flat_map(|accumulator| accumulator.iter())
+            .map(ToString::to_string)
+            .filter(|value| !value.is_empty())
+            .collect();
+        if values.is_empty() {
+            String::from("(none)")
+        } else {
+            values.join(", ")
+        }
+    };
+
+    let mut lines = Vec::new();
+    // trailers seen so far, grouped by key and message, oldest message first
+    let mut history : BTreeMap<String, Vec<Vec<Trailer>>> = BTreeMap::new();
+    let mut current : BTreeMap<String, ValueAccumulator> = BTreeMap::new();
+    for message in messages.iter() {
+        let time = {
+            let gtime = message.time();
+            FixedOffset::east(gtime.offset_minutes()*60).timestamp(gtime.seconds(), 0).format("%Y-%m-%d %H:%M")
+        };
+        let author = message.author().name().unwrap_or("").to_owned();
+
+        if message.id() == issue.id() {
+            let mut initial = issue.initial_message().unwrap_or_abort();
+            lines.push(format!("{} {}: opened {}", time, author, initial.summary().unwrap_or("")));
+        }
+
+        let mut trailers : BTreeMap<String, Vec<Trailer>> = BTreeMap::new();
+        for trailer in message.trailers() {
+            trailers.entry(trailer.key.to_string()).or_insert_with(Vec::new).push(trailer);
+        }
+        for (key, trailers) in trailers {
+            let groups = history.entry(key.clone()).or_insert_with(Vec::new);
+            groups.push(trailers);
+
+            // only the key's own trailers need to be accumulated, latest message first
+            let accumulator = schema
+                .accumulate(groups.iter().rev().flat_map(|group| group.iter().cloned()))
+                .remove(&key);
+            let old = display(current.get(&key));
+            let new = display(accumulator.as_ref());
+            lines.push(format!("{} {}: {} {} -> {}", time, author, key, old, new));
+
+            if let Some(accumulator) = accumulator {
+                current.insert(key, accumulator);
+            }
+        }
+
+        // parents other than the first one are references
+        for mut reference in message.parents().skip(1) {
+            let summary = reference.summary().unwrap_or("").to_owned();
+            lines.push(format!("{0} {1}: reference {2:.3$} {4}", time, author, reference.id(), id_len, summary));
+        }
+    }
+
+    io::stdout().consume_lines(lines).unwrap_or_abort();
+}
+
+
+/// milestone subcommand implementation
+///
+fn milestone_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    // note: "milestone" is always present since it is a required parameter
+    let name = matches.value_of("milestone").unwrap();
+    let closed = repo.closed_states().unwrap_or_abort();
+
+    // collect the remaining issues along with their due dates
+    let mut remaining : Vec<(Option<TrailerValue>, Commit)> = Vec::new();
+    let mut closed_count = 0;
+    for issue in repo.distinct_issues().unwrap_or_abort() {
+        let metadata = repo.issue_metadata(&issue).unwrap_or_abort();
+        if metadata_value(&metadata, trailer::MILESTONE_KEY).map(|m| m.to_string()) != Some(name.to_owned()) {
+            continue;
+        }
+
+        if is_open(&metadata, &closed) {
+            let due = metadata_value(&metadata, trailer::DUE_KEY).cloned();
+            remaining.push((due, repo.find_commit(issue.id()).unwrap_or_abort()));
+        } else {
+            closed_count += 1;
+        }
+    }
+
+    // issues due first come first, issues without due date last
+    remaining.sort_by(|a, b| match (&a.0, &b.0) {
+        (&Some(ref a), &Some(ref b)) => a.cmp(b),
+        (&Some(_), &None) => std::cmp::Ordering::Less,
+        (&None, &Some(_)) => std::cmp::Ordering::Greater,
+        (&None, &None) => std::cmp::Ordering::Equal,
+    });
+
+    let id_len = repo.abbreviation_length(matches).unwrap_or_abort();
+    let header = format!("Milestone {}: {} open, {} closed", name, remaining.len(), closed_count);
+    let lines = remaining
+        .into_iter()
+        .map(|(due, mut commit)| match due {
+            Some(due) => format!("    {0:.1$} {2} (due {3})", commit.id(), id_len, commit.summary().unwrap_or(""), due),
+            None => format!("    {0:.1$} {2}", commit.id(), id_len, commit.summary().unwrap_or("")),
+        });
+    io::stdout().consume_lines(Some(header).into_iter().chain(lines)).unwrap_or_abort();
+}
+
+
+/// mine subcommand implementation
+///
+fn mine_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let mailmap = repo.mailmap().unwrap_or_abort();
+    let closed = repo.closed_states().unwrap_or_abort();
+    let me = match repo.resolve_person(None).unwrap_or_abort() {
+        TrailerValue::Person { email, .. } => email.to_lowercase(),
+        _ => unreachable!(),
+    };
+
+    // check whether a value refers to the user
+    let is_me = |value: &TrailerValue| match *value {
+        TrailerValue::Person { ref name, ref email } => mailmap.canonical(name, email).1.to_lowercase() == me,
+        _ => false,
+    };
+
+    // collect the open issues assigned to or reported by the user, along with
+    // their last activity
+    let mut issues : Vec<(git2::Time, Commit)> = Vec::new();
+    for issue in repo.distinct_issues().unwrap_or_abort() {
+        let metadata = repo.issue_metadata(&issue).unwrap_or_abort();
+        if !is_open(&metadata, &closed) {
+            continue;
+        }
+
+        let initial = repo.find_commit(issue.id()).unwrap_or_abort();
+        let assigned = metadata_value(&metadata, trailer::ASSIGNEE_KEY)
+            .map(|v| is_me(v))
+            .unwrap_or(false);
+        let reported = match metadata_value(&metadata, trailer::REPORTER_KEY) {
+            Some(v) => is_me(v),
+            None => {
+                let author = initial.author();
+                mailmap.canonical(author.name().unwrap_or(""), author.email().unwrap_or(""))
+                       .1
+                       .to_lowercase() == me
+            },
+        };
+
+        if assigned || reported {
+            let time = issue.latest_message().unwrap_or_abort().time();
+            issues.push((time, initial));
+        }
+    }
+
+    // most recently active issues first
+    issues.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let id_len = repo.abbreviation_length(matches).unwrap_or_abort();
+    let lines = issues
+        .into_iter()
+        .map(|(gtime, mut commit)| {
+            let time = FixedOffset::east(gtime.offset_minutes()*60).timestamp(gtime.seconds(), 0);
+            format!("{0:.1$} ({2}) {3}", commit.id(), id_len, time.format("%c"), commit.summary().unwrap_or(""))
+        });
+    io::stdout().consume_lines(lines).unwrap_or_abort();
+}
+
+
 /// new subcommand implementation
 ///
 fn new_impl(repo: &Repository, matches: &clap::ArgMatches) {
     let sig = repo.signature().unwrap_or_abort();
 
+    // get the template, if one was requested
+    let template = matches
+        .value_of("template")
+        .map(|name| repo.template(name).unwrap_or_abort())
+        .unwrap_or_default();
+
     // get the message, either from the command line argument or an editor
     let message = if let Some(m) = message_from_args(matches) {
-        // the message was supplied via the command line
+        // the message was supplied via the command line, so we only use the
+        // metadata from the template
         m.into_iter()
+         .chain(template.iter()
+                        .stripped()
+                        .trailers()
+                        .only_dit()
+                        .map(|t| t.to_string()))
          .chain(repo.prepare_trailers(matches)
                     .unwrap_or_abort()
                     .into_iter()
@@ -276,7 +770,21 @@ fn new_impl(repo: &Repository, matches: &clap::ArgMatches) {
 
         { // write
             let mut file = File::create(path.as_path()).unwrap_or_abort();
-            file.consume_lines(repo.prepare_trailers(matches).unwrap_or_abort()).unwrap_or_abort();
+            let trailers = repo.prepare_trailers(matches).unwrap_or_abort();
+            file.consume_lines(template.iter()).unwrap_or_abort();
+
+            // make sure the trailers end up in a block of trailers
+            let ends_with_trailer = template
+                .iter()
+                .stripped()
+                .last()
+                .map(|line| is_match!(Line::from(line), Line::Trailer(_)))
+                .unwrap_or(true);
+            if !(trailers.is_empty() || ends_with_trailer) {
+                write!(&mut file, "\n").unwrap_or_abort();
+            }
+
+            file.consume_lines(trailers).unwrap_or_abort();
             file.flush().unwrap_or_abort();
         }
 
@@ -292,41 +800,136 @@ fn new_impl(repo: &Repository, matches: &clap::ArgMatches) {
 }
 
 
+/// overdue subcommand implementation
+///
+fn overdue_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let now = TrailerValue::Date(UTC::now().with_timezone(&FixedOffset::east(0)));
+    let closed = repo.closed_states().unwrap_or_abort();
+
+    // collect the open issues with a due date in the past
+    let mut overdue : Vec<(TrailerValue, Commit)> = Vec::new();
+    for issue in repo.distinct_issues().unwrap_or_abort() {
+        let metadata = repo.issue_metadata(&issue).unwrap_or_abort();
+        if !is_open(&metadata, &closed) {
+            continue;
+        }
+
+        match metadata_value(&metadata, trailer::DUE_KEY) {
+            Some(due @ &TrailerValue::Date(_)) if *due < now => {
+                overdue.push((due.clone(), repo.find_commit(issue.id()).unwrap_or_abort()));
+            },
+            _ => {},
+        }
+    }
+
+    // the issues overdue for the longest time first
+    overdue.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let id_len = repo.abbreviation_length(matches).unwrap_or_abort();
+    let lines = overdue
+        .into_iter()
+        .map(|(due, mut commit)| format!("{0:.1$} (due {2}) {3}", commit.id(), id_len, due, commit.summary().unwrap_or("")));
+    io::stdout().consume_lines(lines).unwrap_or_abort();
+}
+
+
 /// push subcommand implementation
 ///
 fn push_impl(repo: &Repository, matches: &clap::ArgMatches) {
     // note: "remote" is always present since it is a required parameter
-    let mut remote = repo.find_remote(matches.value_of("remote").unwrap()).unwrap_or_abort();
+    let name = matches.value_of("remote").unwrap();
+    let mut remote = repo.find_remote(name).unwrap_or_abort();
 
-    // accumulate the refspecs to push
-    let refspecs : Vec<String> = if let Some(issues) = matches.values_of("issue") {
+    // accumulate the refs to push
+    let issues : Vec<Issue> = if let Some(issues) = matches.values_of("issue") {
         // push a specific list of issues
         issues.map(|issue| repo.value_to_issue(issue))
               .abort_on_err()
-              .map(|issue| issue.local_refs(IssueRefType::Any))
-              .abort_on_err()
-              .flat_map(git2::References::names)
-              .abort_on_err()
-              .map(String::from)
               .collect()
     } else {
         repo.issues_with_prefix("refs")
             .abort_on_err()
-            .map(|issue| issue.local_refs(IssueRefType::Any))
-            .abort_on_err()
-            .flat_map(git2::References::names)
-            .abort_on_err()
-            .map(String::from)
             .collect()
     };
+    let mut refnames = Vec::new();
+    for issue in issues {
+        let mut refs = issue.local_refs(IssueRefType::Any).unwrap_or_abort();
+        refnames.extend(refs.names().abort_on_err().map(String::from));
+    }
 
-    // set the options for the push
-    let mut fetch_options = git2::PushOptions::new();
-    fetch_options.remote_callbacks(callbacks::callbacks());
+    // only refs differing from their remote-tracking counterparts are pushed
+    let updates = summary::push_updates(repo, name, refnames).unwrap_or_abort();
 
-    let refspec_refs : Vec<&str> = refspecs.iter().map(String::as_str).collect();
-    remote.push(refspec_refs.as_ref(), Some(&mut fetch_options))
-          .unwrap_or_abort();
+    // Heads which diverged from the ones last fetched are only overwritten if
+    // requested. The push checks whether the remote's heads changed since.
+    let force = matches.is_present("force-with-lease");
+    let diverged : Vec<&RefUpdate> = updates
+        .iter()
+        .filter(|update| !summary::is_fast_forward(repo, update).unwrap_or_abort())
+        .collect();
+    if !diverged.is_empty() && !force {
+        for update in diverged {
+            error!("{} diverged from its counterpart on {}", update.name, name);
+        }
+        error!("Fetch and merge the heads first or use --force-with-lease.");
+        std::process::exit(1);
+    }
+
+    let summary = summary::push(repo, &mut remote, updates, force).unwrap_or_abort();
+    print_summary(repo, &summary, "push", &remote, matches);
+}
+
+
+/// remote subcommand implementation
+///
+fn remote_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let (setup, matches) = match matches.subcommand() {
+        ("setup",       Some(sub_matches)) => (true, sub_matches),
+        ("teardown",    Some(sub_matches)) => (false, sub_matches),
+        _ => {
+            writeln!(io::stderr(), "{}", matches.usage()).ok();
+            std::process::exit(1);
+        },
+    };
+
+    // note: "remote" is always present since it is a required parameter
+    let name = matches.value_of("remote").unwrap();
+    let remote = repo.find_remote(name).unwrap_or_abort();
+    let fetch_refspec = remote.all_issues_refspec().unwrap();
+    let fetch_key = format!("remote.{}.fetch", name);
+    let push_key = format!("remote.{}.push", name);
+
+    if !setup {
+        programs::unset_config_value(repo.path(), &fetch_key, &fetch_refspec).unwrap_or_abort();
+        programs::unset_config_value(repo.path(), &push_key, ALL_ISSUES_PUSH_REFSPEC).unwrap_or_abort();
+
+        // remove the refspec for the current branch added during the setup
+        let push_refspecs = repo.find_remote(name).unwrap_or_abort().push_refspecs().unwrap_or_abort();
+        if push_refspecs.len() == 1 && push_refspecs.get(0) == Some("HEAD") {
+            programs::unset_config_value(repo.path(), &push_key, "HEAD").unwrap_or_abort();
+        }
+        return;
+    }
+
+    if remote.is_dit_configured().unwrap_or_abort() {
+        warn!("Remote {} is already configured for exchanging issues.", name);
+        return;
+    }
+
+    let fetch_refspecs = remote.fetch_refspecs().unwrap_or_abort();
+    if !fetch_refspecs.iter().any(|refspec| refspec == Some(fetch_refspec.as_str())) {
+        repo.remote_add_fetch(name, &fetch_refspec).unwrap_or_abort();
+    }
+
+    // Once a push refspec is configured, plain pushes will only push matching
+    // refs. We retain git's default of pushing the current branch.
+    let push_refspecs = remote.push_refspecs().unwrap_or_abort();
+    if push_refspecs.is_empty() {
+        repo.remote_add_push(name, "HEAD").unwrap_or_abort();
+    }
+    if !push_refspecs.iter().any(|refspec| refspec == Some(ALL_ISSUES_PUSH_REFSPEC)) {
+        repo.remote_add_push(name, ALL_ISSUES_PUSH_REFSPEC).unwrap_or_abort();
+    }
 }
 
 
@@ -403,13 +1006,190 @@ fn reply_impl(repo: &Repository, matches: &clap::ArgMatches) {
          .unwrap_or_abort();
 }
 
+/// search subcommand implementation
+///
+fn search_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    // note: "pattern" is always present since it is a required parameter
+    let pattern = matches.value_of("pattern").unwrap();
+    let authors : Vec<String> = matches
+        .values_of("author")
+        .map(|authors| authors.map(str::to_lowercase).collect())
+        .unwrap_or_default();
+    let closed = if matches.is_present("open") {
+        Some(repo.closed_states().unwrap_or_abort())
+    } else {
+        None
+    };
+    let id_len = repo.abbreviation_length(matches).unwrap_or_abort();
+
+    // issues of aggregated repositories are searched along with our own
+    let aggregated = repo.aggregated_repositories().unwrap_or_abort();
+    let sources : Vec<(Option<&str>, &Repository)> = Some((None, repo))
+        .into_iter()
+        .chain(aggregated.iter().map(|&(ref name, ref repo)| (Some(name.as_str()), repo)))
+        .collect();
+    let qualified = |source: Option<&str>, id: git2::Oid| -> String {
+        let mut id = id.to_string();
+        id.truncate(id_len);
+        match source {
+            Some(source) => format!("{}:{}", source, id),
+            None => id,
+        }
+    };
+
+    if matches.is_present("index") {
+        // the index provides a ranked list of messages
+        let query = IndexQuery::from_str(pattern).unwrap_or_abort();
+        let mut hits = Vec::new();
+        for &(source, repo) in sources.iter() {
+            let mut open_issues = BTreeMap::new();
+            for hit in repo.full_text_index().unwrap_or_abort().search(&query) {
+                if let Some(ref closed) = closed {
+                    // issues may have vanished since they were indexed
+                    let open = open_issues.entry(hit.issue).or_insert_with(|| match repo.find_issue(hit.issue) {
+                        Ok(issue) => is_open(&repo.issue_metadata(&issue).unwrap_or_abort(), closed),
+                        Err(_) => false,
+                    });
+                    if !*open {
+                        continue;
+                    }
+                }
+
+                // messages may have vanished since they were indexed
+                let mut commit = match repo.find_commit(hit.message) {
+                    Ok(commit) => commit,
+                    Err(_) => continue,
+                };
+                let author = commit.author().to_string();
+                if !authors.is_empty() && !authors.iter().any(|a| author.to_lowercase().contains(a.as_str())) {
+                    continue;
+                }
+
+                let subject = commit.summary().unwrap_or("").to_owned();
+                let mut message = hit.message.to_string();
+                message.truncate(id_len);
+                let line = format!("{} {} {}: {}", qualified(source, hit.issue), message, author, subject);
+                hits.push((hit.score, line));
+            }
+        }
+
+        // hits from different repositories are merged by their score
+        hits.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        io::stdout().consume_lines(hits.into_iter().map(|hit| hit.1)).unwrap_or_abort();
+        return;
+    }
+
+    let ignore_case = matches.is_present("ignore-case");
+    let query = if matches.is_present("regex") {
+        Query::regex(pattern, ignore_case)
+    } else {
+        Query::literal(pattern, ignore_case)
+    }.unwrap_or_abort();
+
+    let mut lines = Vec::new();
+    for &(source, repo) in sources.iter() {
+        for issue in repo.issues().abort_on_err() {
+            if let Some(ref closed) = closed {
+                if !is_open(&repo.issue_metadata(&issue).unwrap_or_abort(), closed) {
+                    continue;
+                }
+            }
+
+            // matching messages are displayed along with their position in the
+            // message tree of the issue
+            let mut issue_lines = Vec::new();
+            let messages = issue
+                .message_revwalk()
+                .abort_on_err()
+                .map(|oid| repo.find_commit(oid))
+                .abort_on_err()
+                .into_tree_graph();
+            for (mut elems, mut commit) in messages {
+                if !authors.is_empty() {
+                    let author = commit.author().to_string().to_lowercase();
+                    if !authors.iter().any(|a| author.contains(a.as_str())) {
+                        continue;
+                    }
+                }
+
+                let matching = query.matching_lines(commit.message_lines());
+                if matching.is_empty() {
+                    continue;
+                }
+
+                let subject = commit.summary().unwrap_or("").to_owned();
+                let header = format!("{0:.1$} {2}: {3}", commit.id(), id_len, commit.author(), subject);
+                elems.append(TreeGraphElem::Empty);
+                issue_lines.extend(elems
+                    .commit_iterator()
+                    .zip(Some(header).into_iter().chain(matching.into_iter().map(|line| format!("    {}", line))))
+                    .map(|line| format!("{} {}", line.0, line.1)));
+            }
+
+            if !issue_lines.is_empty() {
+                let mut initial = repo.find_commit(issue.id()).unwrap_or_abort();
+                lines.push(format!("Issue {} {}", qualified(source, issue.id()), initial.summary().unwrap_or("")));
+                lines.extend(issue_lines);
+                lines.push(String::new());
+            }
+        }
+    }
+
+    io::stdout().consume_lines(lines).unwrap_or_abort();
+}
+
+
+/// set-due subcommand implementation
+///
+fn set_due_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let issue = repo.cli_issue(matches).unwrap_or_abort();
+    let due = match matches.value_of("date") {
+        Some(date) => TrailerValue::date_from_slice(date)
+            .ok_or_else(|| Error::from_kind(EK::MalformedDate(date.to_owned())))
+            .unwrap_or_abort(),
+        None => TrailerValue::String(String::new()),
+    };
+
+    let trailer = Trailer { key: String::from(trailer::DUE_KEY).into(), value: due };
+    if repo.set_metadata(&issue, trailer).unwrap_or_abort().is_none() {
+        warn!("No commit was created because the due date was not changed.");
+    }
+}
+
+
+/// set-milestone subcommand implementation
+///
+fn set_milestone_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let issue = repo.cli_issue(matches).unwrap_or_abort();
+    let trailer = Trailer::new(trailer::MILESTONE_KEY, matches.value_of("milestone").unwrap_or(""));
+    if repo.set_metadata(&issue, trailer).unwrap_or_abort().is_none() {
+        warn!("No commit was created because the milestone was not changed.");
+    }
+}
+
+
 /// show subcommand implementation
 ///
 fn show_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    // issues of aggregated repositories are referred to by qualified ids
+    let (source, id) = qualified_issue(matches.value_of("issue").unwrap());
+    let aggregated;
+    let repo = match source {
+        Some(name) => {
+            aggregated = repo.aggregated_repository(name).unwrap_or_abort();
+            &aggregated
+        },
+        None => repo,
+    };
+
     let id_len = repo.abbreviation_length(matches).unwrap_or_abort();
+    let seen = repo.seen_messages().unwrap_or_abort();
 
     // translate commit to lines representing the commit
     let commit_lines = |mut commit: Commit| -> Vec<String> {
+        // messages the user didn't see yet are highlighted
+        let new = if seen.contains(&commit.id()) { "" } else { " (new)" };
+
         // the function is this ugly to comply to the old bash interface
         if matches.is_present("msgtree") {
             // With the "tree" option, we only display subjects in a short
@@ -417,13 +1197,13 @@ fn show_impl(repo: &Repository, matches: &clap::ArgMatches) {
 
             // NOTE: the commit is borrowed mutable in order to get the subject
             let subject = commit.summary().unwrap_or("").to_owned();
-            vec![format!("{0:.1$} {2}: {3}", commit.id(), id_len, commit.author(), subject)]
+            vec![format!("{0:.1$} {2}: {3}{4}", commit.id(), id_len, commit.author(), subject, new)]
         } else {
             let mut id = commit.id().to_string();
             id.truncate(id_len);
             // Regular "long" format
             vec![
-                id,
+                id + new,
                 commit.author().to_string(),
                 String::new()
             ].into_iter()
@@ -434,7 +1214,7 @@ fn show_impl(repo: &Repository, matches: &clap::ArgMatches) {
     };
 
     // first, get us an iterator over all the commits
-    let issue = repo.cli_issue(matches).unwrap_or_abort();
+    let issue = repo.value_to_issue(id).unwrap_or_abort();
     let mut commits : Vec<(TreeGraphElemLine, Commit)> =
         if matches.is_present("initial") {
             vec![(
@@ -450,6 +1230,7 @@ fn show_impl(repo: &Repository, matches: &clap::ArgMatches) {
                 .into_tree_graph()
                 .collect()
         };
+    let displayed : Vec<git2::Oid> = commits.iter().map(|commit| commit.1.id()).collect();
 
     // Decide on the order in which the messages will be printed.
     if matches.is_present("tree") {
@@ -460,6 +1241,67 @@ fn show_impl(repo: &Repository, matches: &clap::ArgMatches) {
         }
     };
 
+    // If requested, we print the accumulated metadata before anything else.
+    let metadata : Vec<String> = if matches.is_present("metadata") {
+        let metadata = repo.issue_metadata(&issue).unwrap_or_abort();
+        metadata_trailers(metadata)
+            .into_iter()
+            .map(|trailer| trailer.to_string())
+            .chain(Some(String::new()).into_iter())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // Duplicates of the issue are listed before the messages. Accumulating the
+    // metadata of every issue is expensive, so we only do so for issues which
+    // were marked as a duplicate of this one at some point.
+    let duplicates : Vec<String> = {
+        let id = issue.id().to_string();
+        let duplicates : Vec<String> = repo
+            .distinct_issues()
+            .unwrap_or_abort()
+            .into_iter()
+            .filter(|other| carries_trailer(other, repo, trailer::DUPLICATE_KEY, &id).unwrap_or_abort())
+            .filter(|other| duplicate_of(&repo.issue_metadata(other).unwrap_or_abort()) == Some(issue.id()))
+            .map(|other| {
+                let mut initial = repo.find_commit(other.id()).unwrap_or_abort();
+                format!("    {0:.1$} {2}", other.id(), id_len, initial.summary().unwrap_or(""))
+            })
+            .collect();
+        if duplicates.is_empty() {
+            duplicates
+        } else {
+            Some(String::from("Duplicates:"))
+                .into_iter()
+                .chain(duplicates)
+                .chain(Some(String::new()))
+                .collect()
+        }
+    };
+
+    // If the issue is a patch set, we list the commits of the series before
+    // the messages.
+    let series : Vec<String> = issue
+        .patch_set_revwalk()
+        .unwrap_or_abort()
+        .map(|revwalk| {
+            let base = issue.patch_set_base().unwrap_or_abort().unwrap();
+            Some(format!("Patch set on top of {0:.1$}:", base, id_len))
+                .into_iter()
+                .chain(revwalk
+                    .abort_on_err()
+                    .map(|id| repo.find_commit(id))
+                    .abort_on_err()
+                    .map(|mut commit| {
+                        let summary = commit.summary().unwrap_or("").to_owned();
+                        format!("    {0:.1$} {2}", commit.id(), id_len, summary)
+                    }))
+                .chain(Some(String::new()).into_iter())
+                .collect()
+        })
+        .unwrap_or_default();
+
     // Transform the simple graph element line into an iterator over lines to
     // print via multiple steps.
     let graph = commits
@@ -480,37 +1322,222 @@ fn show_impl(repo: &Repository, matches: &clap::ArgMatches) {
     // spawn a pager and write the graph
     let mut pager = programs::pager(repo.config().unwrap_or_abort())
         .unwrap_or_abort();
-    pager.stdin.as_mut().unwrap().consume_lines(graph).unwrap_or_abort();
+    pager.stdin.as_mut().unwrap().consume_lines(metadata.into_iter().chain(duplicates).chain(series).chain(graph)).unwrap_or_abort();
 
     // don't trash the shell by exitting with a child still printing to it
     let result = pager.wait().unwrap_or_abort();
     if !result.success() {
         std::process::exit(result.code().unwrap_or(1));
     }
+
+    if !matches.is_present("keep-unread") {
+        repo.mark_seen(displayed.into_iter().filter(|id| !seen.contains(id))).unwrap_or_abort();
+    }
 }
 
-/// tag subcommand implementation
+/// stats subcommand implementation
 ///
-fn tag_impl(repo: &Repository, matches: &clap::ArgMatches) {
-    // get the head for the issue to tag
-    let mut issue_head = repo
-        .cli_issue(matches)
-        .unwrap_or_abort()
-        .local_head()
+fn stats_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    // note: the values are restricted to valid formats and periods
+    let format = Format::from_name(matches.value_of("format").unwrap_or("text")).unwrap();
+    let period_name = matches.value_of("period").unwrap_or("week");
+    let period = Period::from_str(period_name).unwrap_or_abort();
+    let closed = repo.closed_states().unwrap_or_abort();
+    let schema = repo.metadata_schema().unwrap_or_abort();
+    let walk = repo.metadata_walk().unwrap_or_abort();
+
+    let mut by_status = BTreeMap::new();
+    let mut by_type = BTreeMap::new();
+    let mut by_label = BTreeMap::new();
+    let mut by_assignee = BTreeMap::new();
+    let mut opened = BTreeMap::new();
+    let mut closed_in = BTreeMap::new();
+    let mut times_to_close = Vec::new();
+    let mut times_to_first_reply = Vec::new();
+    for issue in repo.distinct_issues().unwrap_or_abort() {
+        // both the metadata and the transitions are derived from the same
+        // messages
+        let messages = repo.metadata_messages(&issue, &walk).unwrap_or_abort();
+        let mut metadata = schema.accumulate(messages.iter().flat_map(|message| message.trailers()));
+        let value = |key| metadata_value(&metadata, key)
+            .map(ToString::to_string)
+            .unwrap_or_else(|| String::from("(none)"));
+        *by_status.entry(value(trailer::STATUS_KEY)).or_insert(0) += 1;
+        *by_type.entry(value(trailer::TYPE_KEY)).or_insert(0) += 1;
+        *by_assignee.entry(value(trailer::ASSIGNEE_KEY)).or_insert(0) += 1;
+        for label in metadata_values(&mut metadata, trailer::LABEL_KEY) {
+            *by_label.entry(label.to_string()).or_insert(0) += 1;
+        }
+
+        let lifecycle = Lifecycle::from_messages(&issue, messages).unwrap_or_abort();
+        *opened.entry(period.start(lifecycle.created)).or_insert(0) += 1;
+        closed_in.entry(period.start(lifecycle.created)).or_insert(0);
+        if let Some(time) = lifecycle.closed(&closed) {
+            opened.entry(period.start(time)).or_insert(0);
+            *closed_in.entry(period.start(time)).or_insert(0) += 1;
+        }
+        times_to_close.extend(lifecycle.time_to_close(&closed));
+        times_to_first_reply.extend(lifecycle.time_to_first_reply());
+    }
+
+    let table = |name: &str, title: String, counts: BTreeMap<String, usize>| Table {
+        name: name.to_owned(),
+        title: title,
+        rows: counts.into_iter().map(|(key, count)| (key, Value::Count(count))).collect(),
+    };
+    let dates = |counts: BTreeMap<NaiveDate, usize>| -> BTreeMap<String, usize> {
+        counts.into_iter().map(|(date, count)| (date.format("%Y-%m-%d").to_string(), count)).collect()
+    };
+
+    let mut medians = Table::new("median", "Median durations");
+    medians.rows.push((String::from("time to close"), Value::Duration(median(times_to_close))));
+    medians.rows.push((String::from("time to first reply"), Value::Duration(median(times_to_first_reply))));
+
+    let report = Report(vec![
+        table("status", String::from("Issues by status"), by_status),
+        table("type", String::from("Issues by type"), by_type),
+        table("label", String::from("Issues by label"), by_label),
+        table("assignee", String::from("Issues by assignee"), by_assignee),
+        table("opened", format!("Opened per {}", period_name), dates(opened)),
+        table("closed", format!("Closed per {}", period_name), dates(closed_in)),
+        medians,
+    ]);
+    report.write(&mut io::stdout(), format).unwrap_or_abort();
+}
+
+
+/// submit subcommand implementation
+///
+fn submit_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let sig = repo.signature().unwrap_or_abort();
+
+    // note: "range" is always present since it is a required parameter
+    let range = matches.value_of("range").unwrap();
+    let (base, tip) = repo.value_to_range(range).unwrap_or_abort();
+    let series : Vec<Commit> = repo
+        .series_revwalk(base.id(), tip.id())
+        .abort_on_err()
+        .map(|id| repo.find_commit(id))
+        .abort_on_err()
+        .collect();
+    if series.is_empty() {
+        error!("No commits in range '{}'", range);
+        std::process::exit(1);
+    }
+
+    // the trailers marking the new issue as a patch set
+    let mut trailers = repo.prepare_trailers(matches).unwrap_or_abort();
+    trailers.push(Trailer::new(trailer::TYPE_KEY, trailer::PATCH_SET_TYPE));
+    trailers.push(Trailer::new(trailer::PATCH_BASE_KEY, base.id().to_string().as_ref()));
+
+    // get the message, either from the command line argument or an editor
+    let message = if let Some(m) = message_from_args(matches) {
+        // the message was supplied via the command line
+        m.into_iter()
+         .chain(trailers.into_iter().map(|t| t.to_string()))
+         .collect()
+    } else {
+        // we need an editor
+
+        // get the path where we want to edit the message
+        let path = repo.commitmsg_edit_path(matches);
+
+        { // write
+            let mut file = File::create(path.as_path()).unwrap_or_abort();
+
+            // a series consisting of a single patch makes for a nice subject
+            if series.len() == 1 {
+                write!(&mut file, "{}", series[0].summary().unwrap_or("")).unwrap_or_abort();
+            }
+            write!(&mut file, "\n\n").unwrap_or_abort();
+
+            writeln!(&mut file, "# Commits in this patch set:").unwrap_or_abort();
+            for commit in series.iter() {
+                writeln!(&mut file, "#   {} {}", commit.id(), commit.summary().unwrap_or(""))
+                    .unwrap_or_abort();
+            }
+            write!(&mut file, "\n").unwrap_or_abort();
+
+            file.consume_lines(trailers).unwrap_or_abort();
+            file.flush().unwrap_or_abort();
+        }
+
+        repo.get_commit_msg(path).unwrap_or_abort()
+    }.into_iter().collect_string();
+
+    // commit the message on top of the series
+    let tree = tip.tree().unwrap_or_abort();
+    let id = repo
+        .create_issue(&sig, &sig, message.trim(), &tree, vec![&tip])
         .unwrap_or_abort();
-    let mut head_commit = issue_head
-        .peel(git2::ObjectType::Commit)
+    println!("[dit][submit] {}", id);
+}
+
+/// sync subcommand implementation
+///
+fn sync_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let remotes : Vec<String> = match matches.values_of("remote") {
+        Some(remotes) => remotes.map(String::from).collect(),
+        None => repo
+            .remotes()
+            .unwrap_or_abort()
+            .iter()
+            .filter_map(|name| name.map(String::from))
+            .collect(),
+    };
+
+    // We fetch from all remotes before pushing anything, so that heads moved
+    // on one remote are propagated to the others.
+    for name in remotes.iter() {
+        let mut remote = repo.find_remote(name).unwrap_or_abort();
+        let refspec = remote.all_issues_refspec().unwrap();
+        let summary = summary::fetch(repo, &mut remote, &[refspec.as_str()], false).unwrap_or_abort();
+        print_summary(repo, &summary, "fetch", &remote, matches);
+    }
+
+    let local : Vec<String> = summary::dit_refs(repo)
         .unwrap_or_abort()
-        .into_commit()
-        .ok()
-        .unwrap();
+        .into_iter()
+        .map(|(refname, _)| refname)
+        .filter(|refname| refname.starts_with("refs/dit/"))
+        .collect();
+    for name in remotes.iter() {
+        let mut remote = repo.find_remote(name).unwrap_or_abort();
+
+        // diverged heads are reported rather than pushed
+        let updates = summary::push_updates(repo, name, local.iter().cloned()).unwrap_or_abort();
+        let summary = summary::push(repo, &mut remote, updates, false).unwrap_or_abort();
+        print_summary(repo, &summary, "push", &remote, matches);
+    }
+}
+
+
+/// Print the summary of a fetch or push
+///
+/// Depending on the command line, the summary is printed in either the human
+/// readable or the porcelain format.
+///
+fn print_summary(repo: &Repository, summary: &Summary, direction: &str, remote: &git2::Remote, matches: &clap::ArgMatches) {
+    let name = remote.name().unwrap_or_default();
+    if matches.is_present("porcelain") {
+        io::stdout().consume_lines(summary.porcelain(direction, name)).unwrap_or_abort();
+    } else {
+        let id_len = repo.abbreviation_length(matches).unwrap_or_abort();
+        let verb = if direction == "fetch" { "Fetched from" } else { "Pushed to" };
+        println!("{} {}: {}", verb, name, summary.describe(id_len));
+    }
+}
+
+
+/// tag subcommand implementation
+///
+fn tag_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let issue = repo.cli_issue(matches).unwrap_or_abort();
 
     if matches.is_present("list") {
         // we only list the metadata
-        let trailers = repo.issue_messages_iter(head_commit)
-            .abort_on_err()
-            .flat_map(|c| c.trailers());
-        io::stdout().consume_lines(trailers).unwrap_or_abort();
+        let metadata = repo.issue_metadata(&issue).unwrap_or_abort();
+        io::stdout().consume_lines(metadata_trailers(metadata)).unwrap_or_abort();
         return;
     }
 
@@ -518,33 +1545,179 @@ fn tag_impl(repo: &Repository, matches: &clap::ArgMatches) {
 
     // get references and trailers for the new commit
     let references = repo.cli_references(matches).unwrap_or_abort();
+    let schema = repo.metadata_schema().unwrap_or_abort();
     let trailers : Vec<Trailer> = matches.values_of("set-status")
                                          .into_iter()
                                          .flat_map(|values| values)
                                          .map(Trailer::from_str)
                                          .abort_on_err()
+                                         .inspect(|t| schema.validate(t).unwrap_or_abort())
                                          .collect();
     if references.is_empty() && trailers.is_empty() {
         warn!("No commit was created because no reference or tags were supplied.");
         return;
     }
 
-    // construct the message
-    let sig = repo.signature().unwrap_or_abort();
-    let message = [head_commit.reply_subject().unwrap_or_default(), String::new()]
-        .to_vec()
-        .into_iter()
-        .chain(trailers.into_iter().map(|t| t.to_string()))
-        .collect_string();
-    let tree = repo.empty_tree().unwrap_or_abort();
-    let parent_refs : Vec<&Commit> = Some(&head_commit).into_iter().chain(references.iter()).collect();
-    let new = repo
-        .commit(None, &sig, &sig, message.trim(), &tree, &parent_refs)
+    repo.tag_issue(&issue, &trailers, &references).unwrap_or_abort();
+}
+
+
+/// transfer subcommand implementation
+///
+fn transfer_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let issue = repo.cli_issue(matches).unwrap_or_abort();
+    // note: "target" is always present since it is a required parameter
+    let target = matches.value_of("target").unwrap();
+    let mut remote = repo
+        .find_remote(target)
+        .or_else(|_| repo.remote_anonymous(target))
         .unwrap_or_abort();
 
-    // update the head reference
-    issue_head.set_target(new, "Issue head updated by git-dit-tag")
-              .unwrap_or_abort();
+    // The refs of the issue are transferred from the local repository or, if
+    // the issue has no local head, from the remote whose head is used. Either
+    // way, they are stored under the names of local refs on the target.
+    let head = issue.head().unwrap_or_abort();
+    let head_id = head.target().unwrap();
+    let ref_part = format!("dit/{}/", issue.ref_part());
+    let prefix = head
+        .name()
+        .and_then(|name| name.find(&ref_part).map(|pos| name[..pos].to_owned()))
+        .unwrap();
+    let head_name = format!("refs/dit/{}/head", issue.ref_part());
+    let mut refspecs : Vec<String> = issue
+        .all_refs(IssueRefType::Any)
+        .unwrap_or_abort()
+        .names()
+        .abort_on_err()
+        .filter(|name| name.starts_with(&prefix) && name[prefix.len()..].starts_with(&ref_part))
+        .map(|name| format!("{}:refs/{}", name, &name[prefix.len()..]))
+        .collect();
+
+    // When moving the issue, the head on the target is set to a message
+    // marking the issue's origin. We need a temporary ref for pushing it.
+    let marker_ref = if matches.is_present("copy") {
+        None
+    } else {
+        let source = matches
+            .value_of("source")
+            .map(String::from)
+            .unwrap_or_else(|| repo.workdir().unwrap_or(repo.path()).to_string_lossy().into_owned());
+        let trailer = Trailer::new(trailer::MOVED_FROM_KEY, &source);
+
+        let subject = repo.find_commit(head_id).unwrap_or_abort().reply_subject();
+        let head = repo.find_commit(head_id).unwrap_or_abort();
+        let sig = repo.signature().unwrap_or_abort();
+        let message = [subject.unwrap_or_default(), String::new(), trailer.to_string()]
+            .to_vec()
+            .into_iter()
+            .collect_string();
+        let tree = repo.empty_tree().unwrap_or_abort();
+        let marker = repo.commit(None, &sig, &sig, message.trim(), &tree, &[&head]).unwrap_or_abort();
+
+        let name = format!("refs/dit-transfer/{}", issue);
+        repo.reference(&name, marker, true, "git-dit: transfer marker").unwrap_or_abort();
+        refspecs.retain(|refspec| !refspec.ends_with(&format!(":{}", head_name)));
+        refspecs.push(format!("{}:{}", name, head_name));
+        Some(name)
+    };
+
+    let mut push_options = git2::PushOptions::new();
+    push_options.remote_callbacks(callbacks::callbacks());
+    let refspec_refs : Vec<&str> = refspecs.iter().map(String::as_str).collect();
+    let result = remote.push(refspec_refs.as_ref(), Some(&mut push_options));
+
+    if let Some(name) = marker_ref {
+        repo.find_reference(&name).and_then(|mut r| r.delete()).unwrap_or_abort();
+        result.unwrap_or_abort();
+        let location = remote.url().unwrap_or(target).to_owned();
+        repo.tag_issue(&issue, &[Trailer::new(trailer::MOVED_TO_KEY, &location)], &[]).unwrap_or_abort();
+    } else {
+        result.unwrap_or_abort();
+    }
+}
+
+
+/// unassign subcommand implementation
+///
+fn unassign_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    let issue = repo.cli_issue(matches).unwrap_or_abort();
+
+    let metadata = repo.issue_metadata(&issue).unwrap_or_abort();
+    if metadata_value(&metadata, trailer::ASSIGNEE_KEY).is_none() {
+        warn!("No commit was created because nobody is assigned to the issue.");
+        return;
+    }
+
+    // an empty value indicates that nobody is assigned
+    repo.tag_issue(&issue, &[Trailer::new(trailer::ASSIGNEE_KEY, "")], &[]).unwrap_or_abort();
+}
+
+
+/// whatsnew subcommand implementation
+///
+fn whatsnew_impl(repo: &Repository, matches: &clap::ArgMatches) {
+    // An explicit point in time is compared against the time of the messages.
+    // Otherwise, we report the messages which were not reachable from any dit
+    // reference at the time of the last query, regardless of their time.
+    let tips : Vec<git2::Oid> = summary::dit_refs(repo).unwrap_or_abort().values().cloned().collect();
+    let since = matches.value_of("since").map(|since| match TrailerValue::date_from_slice(since) {
+        Some(TrailerValue::Date(date)) => date.timestamp(),
+        _ => repo.value_to_commit(since).unwrap_or_abort().time().seconds(),
+    });
+    let known = repo.whatsnew_bookmark().unwrap_or_abort().unwrap_or_default();
+
+    let id_len = repo.abbreviation_length(matches).unwrap_or_abort();
+    let mut new_issues = Vec::new();
+    let mut new_replies = Vec::new();
+    let mut status_changes = Vec::new();
+    for issue in repo.distinct_issues().unwrap_or_abort() {
+        let messages = match since {
+            Some(since) => issue.messages_since(since),
+            None => issue.messages_unknown_to(&known),
+        }.unwrap_or_abort();
+        if messages.is_empty() {
+            continue;
+        }
+
+        let mut initial = repo.find_commit(issue.id()).unwrap_or_abort();
+        let line = format!("    {0:.1$} {2}", issue.id(), id_len, initial.summary().unwrap_or(""));
+
+        let replies = messages.iter().filter(|message| message.id() != issue.id()).count();
+        if replies < messages.len() {
+            new_issues.push(line.clone());
+        }
+        if replies > 0 {
+            new_replies.push(format!("{} ({} new)", line, replies));
+        }
+
+        // the messages are ordered newest first
+        let status = messages
+            .iter()
+            .filter_map(|message| message
+                .trailers()
+                .filter(|t| t.key.as_ref() == trailer::STATUS_KEY)
+                .last()
+            )
+            .next();
+        if let Some(status) = status {
+            status_changes.push(format!("{}: {}", line, status.value));
+        }
+    }
+
+    let sections = vec![
+        ("New issues:", new_issues),
+        ("New replies:", new_replies),
+        ("Status changes:", status_changes),
+    ];
+    let lines = sections
+        .into_iter()
+        .filter(|&(_, ref lines)| !lines.is_empty())
+        .flat_map(|(header, lines)| Some(String::from(header)).into_iter().chain(lines));
+    io::stdout().consume_lines(lines).unwrap_or_abort();
+
+    if since.is_none() {
+        repo.set_whatsnew_bookmark(tips).unwrap_or_abort();
+    }
 }
 
 
@@ -584,19 +1757,39 @@ fn main() {
 
     match matches.subcommand() {
         // Plumbing subcommands
-        ("check-message",               Some(sub_matches)) => check_message(sub_matches),
+        ("check-message",               Some(sub_matches)) => check_message(&repo, sub_matches),
         ("create-message",              Some(sub_matches)) => create_message(&repo, sub_matches),
         ("find-tree-init-hash",         Some(sub_matches)) => find_tree_init_hash(&repo, sub_matches),
         ("get-issue-metadata",          Some(sub_matches)) => get_issue_metadata(&repo, sub_matches),
         ("get-issue-tree-init-hashes",  Some(sub_matches)) => get_issue_tree_init_hashes(&repo, sub_matches),
         // Porcelain subcommands
+        ("assign",  Some(sub_matches)) => assign_impl(&repo, sub_matches),
+        ("checkout", Some(sub_matches)) => checkout_impl(&repo, sub_matches),
+        ("duplicate", Some(sub_matches)) => duplicate_impl(&repo, sub_matches),
         ("fetch",   Some(sub_matches)) => fetch_impl(&repo, sub_matches),
+        ("label",   Some(sub_matches)) => label_impl(&repo, sub_matches),
+        ("labels",  Some(sub_matches)) => labels_impl(&repo, sub_matches),
+        ("lint",    Some(sub_matches)) => lint_impl(&repo, sub_matches),
         ("list",    Some(sub_matches)) => list_impl(&repo, sub_matches),
+        ("log",     Some(sub_matches)) => log_impl(&repo, sub_matches),
+        ("milestone", Some(sub_matches)) => milestone_impl(&repo, sub_matches),
+        ("mine",    Some(sub_matches)) => mine_impl(&repo, sub_matches),
         ("new",     Some(sub_matches)) => new_impl(&repo, sub_matches),
+        ("overdue", Some(sub_matches)) => overdue_impl(&repo, sub_matches),
         ("push",    Some(sub_matches)) => push_impl(&repo, sub_matches),
+        ("remote",  Some(sub_matches)) => remote_impl(&repo, sub_matches),
         ("reply",   Some(sub_matches)) => reply_impl(&repo, sub_matches),
+        ("search",  Some(sub_matches)) => search_impl(&repo, sub_matches),
+        ("set-due", Some(sub_matches)) => set_due_impl(&repo, sub_matches),
+        ("set-milestone", Some(sub_matches)) => set_milestone_impl(&repo, sub_matches),
         ("show",    Some(sub_matches)) => show_impl(&repo, sub_matches),
+        ("stats",   Some(sub_matches)) => stats_impl(&repo, sub_matches),
+        ("submit",  Some(sub_matches)) => submit_impl(&repo, sub_matches),
+        ("sync",    Some(sub_matches)) => sync_impl(&repo, sub_matches),
         ("tag",     Some(sub_matches)) => tag_impl(&repo, sub_matches),
+        ("transfer", Some(sub_matches)) => transfer_impl(&repo, sub_matches),
+        ("unassign", Some(sub_matches)) => unassign_impl(&repo, sub_matches),
+        ("whatsnew", Some(sub_matches)) => whatsnew_impl(&repo, sub_matches),
         // Unknown subcommands
         ("", _) => {
             writeln!(io::stderr(), "{}", matches.usage()).ok();
@@ -8,10 +8,10 @@
 //
 
 use std::env::var as env_var;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
 
-use git2::Config;
+use git2::{Config, Oid};
 
 use error::*;
 use error::ErrorKind as EK;
@@ -95,3 +95,56 @@ pub fn pager(config: Config) -> Result<Child> {
         })
 }
 
+
+/// Verify the signature of a commit
+///
+/// The signature is verified using `git verify-commit` in the repository with
+/// the git directory supplied. Returns whether the commit carries a good
+/// signature.
+///
+pub fn verify_commit(git_dir: &Path, id: Oid) -> Result<bool> {
+    Command::new("git")
+        .arg("--git-dir")
+        .arg(git_dir)
+        .arg("verify-commit")
+        .arg(id.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .chain_err(|| EK::WrappedIOError)
+}
+
+
+/// Remove a value from a multivar in a repository's configuration
+///
+/// The value is removed using `git config --unset-all`, since libgit2 doesn't
+/// provide a way to do so. All occurrences of the exact value supplied are
+/// removed.
+///
+pub fn unset_config_value(git_dir: &Path, name: &str, value: &str) -> Result<()> {
+    // the value is matched as a regular expression
+    let pattern : String = value
+        .chars()
+        .flat_map(|c| match c {
+            '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect();
+
+    let status = Command::new("git")
+        .arg("--git-dir")
+        .arg(git_dir)
+        .arg("config")
+        .arg("--unset-all")
+        .arg(name)
+        .arg(format!("^{}$", pattern))
+        .status()
+        .chain_err(|| EK::WrappedIOError)?;
+
+    // git exits with status 5 if there was nothing to remove
+    match status.code() {
+        Some(0) | Some(5) => Ok(()),
+        _ => Err(Error::from_kind(EK::ChildError)),
+    }
+}
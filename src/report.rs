@@ -0,0 +1,189 @@
+//   git-dit - the distributed issue tracker for git
+//   Copyright (C) 2017 Matthias Beyer <mail@beyermatthias.de>
+//   Copyright (C) 2017 Julian Ganz <neither@nut.email>
+//
+//   This program is free software; you can redistribute it and/or modify
+//   it under the terms of the GNU General Public License version 2 as
+//   published by the Free Software Foundation.
+//
+
+//! Reports
+//!
+//! This module provides the `Report` type, a sequence of tables mapping keys to
+//! values, which may be written in one of several formats. In text form,
+//! durations are displayed in a human readable way. In CSV and JSON, they are
+//! given in seconds.
+//!
+
+use std::io::{self, Write};
+
+
+/// Output format of a report
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+impl Format {
+    /// Get the format with a given name
+    ///
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text"  => Some(Format::Text),
+            "csv"   => Some(Format::Csv),
+            "json"  => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+
+/// Value in a report
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Count(usize),
+    Duration(Option<i64>),
+}
+
+
+/// Table in a report
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table {
+    /// Name used in machine readable formats
+    pub name: String,
+    /// Title used in the text format
+    pub title: String,
+    pub rows: Vec<(String, Value)>,
+}
+
+impl Table {
+    pub fn new<N, T>(name: N, title: T) -> Self
+        where N: Into<String>,
+              T: Into<String>
+    {
+        Table { name: name.into(), title: title.into(), rows: Vec::new() }
+    }
+}
+
+
+/// Report consisting of tables
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report(pub Vec<Table>);
+
+impl Report {
+    /// Write the report in a given format
+    ///
+    pub fn write<W: Write>(&self, out: &mut W, format: Format) -> io::Result<()> {
+        match format {
+            Format::Text    => self.write_text(out),
+            Format::Csv     => self.write_csv(out),
+            Format::Json    => self.write_json(out),
+        }
+    }
+
+    fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let width = self.0
+            .iter()
+            .flat_map(|table| table.rows.iter())
+            .map(|row| row.0.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for (n, table) in self.0.iter().enumerate() {
+            if n > 0 {
+                writeln!(out, "")?;
+            }
+            writeln!(out, "{}:", table.title)?;
+            for &(ref key, ref value) in table.rows.iter() {
+                let value = match *value {
+                    Value::Count(count) => count.to_string(),
+                    Value::Duration(Some(duration)) => human_duration(duration),
+                    Value::Duration(None) => String::from("-"),
+                };
+                writeln!(out, "    {0:1$}  {2}", key, width, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "table,key,value")?;
+        for table in self.0.iter() {
+            for &(ref key, ref value) in table.rows.iter() {
+                let value = match *value {
+                    Value::Count(count) => count.to_string(),
+                    Value::Duration(duration) => duration.map(|d| d.to_string()).unwrap_or_default(),
+                };
+                writeln!(out, "{},{},{}", csv_field(&table.name), csv_field(key), value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{{")?;
+        for (n, table) in self.0.iter().enumerate() {
+            writeln!(out, "  {}: {{", json_string(&table.name))?;
+            for (m, &(ref key, ref value)) in table.rows.iter().enumerate() {
+                let value = match *value {
+                    Value::Count(count) => count.to_string(),
+                    Value::Duration(duration) => duration.map(|d| d.to_string()).unwrap_or_else(|| String::from("null")),
+                };
+                let sep = if m + 1 < table.rows.len() { "," } else { "" };
+                writeln!(out, "    {}: {}{}", json_string(key), value, sep)?;
+            }
+            writeln!(out, "  }}{}", if n + 1 < self.0.len() { "," } else { "" })?;
+        }
+        writeln!(out, "}}")
+    }
+}
+
+
+/// Format a duration given in seconds in a human readable way
+///
+fn human_duration(duration: i64) -> String {
+    let (days, rest) = (duration / 86400, duration % 86400);
+    let (hours, rest) = (rest / 3600, rest % 3600);
+    let minutes = rest / 60;
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+
+/// Quote a CSV field if necessary
+///
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+
+/// Format a string as a JSON string literal
+///
+fn json_string(s: &str) -> String {
+    let mut retval = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"'     => retval.push_str("\\\""),
+            '\\'    => retval.push_str("\\\\"),
+            '\n'    => retval.push_str("\\n"),
+            '\t'    => retval.push_str("\\t"),
+            c if (c as u32) < 0x20 => retval.push_str(&format!("\\u{:04x}", c as u32)),
+            c       => retval.push(c),
+        }
+    }
+    retval.push('"');
+    retval
+}
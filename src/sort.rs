@@ -0,0 +1,174 @@
+//   git-dit - the distributed issue tracker for git
+//   Copyright (C) 2017 Matthias Beyer <mail@beyermatthias.de>
+//   Copyright (C) 2017 Julian Ganz <neither@nut.email>
+//
+//   This program is free software; you can redistribute it and/or modify
+//   it under the terms of the GNU General Public License version 2 as
+//   published by the Free Software Foundation.
+//
+
+//! Sorting of issues
+//!
+//! This module provides the `SortKey` type, representing a single criterion by
+//! which issues may be ordered. Sort keys are specified as `<field>[:<order>]`,
+//! where the order is either "asc" or "desc" and the field is one of "created",
+//! "updated", "replies", "priority", "severity" or the name of an arbitrary
+//! metadata key.
+//!
+//! Values of the priority and severity of issues are ranked according to the
+//! levels configured via `dit.priority.levels` and `dit.severity.levels`, given
+//! as comma separated lists from the lowest to the highest level.
+//!
+
+use git2;
+use libgitdit::Issue;
+use libgitdit::message::accumulation::ValueAccumulator;
+use libgitdit::message::trailer::{self, TrailerValue};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use error::*;
+use error::ErrorKind as EK;
+use util::metadata_value;
+
+
+/// Field by which issues may be sorted
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum SortField {
+    /// Time of the initial message
+    Created,
+    /// Time of the latest message
+    Updated,
+    /// Number of messages replying to the initial message
+    Replies,
+    /// Value of a metadata key, ranked according to the levels supplied
+    Metadata(String, Vec<String>),
+}
+
+
+/// Criterion for sorting issues
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl SortKey {
+    /// Create a sort key from its specification
+    ///
+    /// If no order is specified, the issues will be sorted in descending order.
+    /// The levels of the priority and severity are taken from the config.
+    ///
+    pub fn from_spec(spec: &str, config: &git2::Config) -> Result<Self> {
+        let (name, descending) = match spec.rfind(':') {
+            Some(pos) => match &spec[pos + 1..] {
+                "asc"   => (&spec[..pos], false),
+                "desc"  => (&spec[..pos], true),
+                _ => return Err(Error::from_kind(EK::MalformedSortKey(spec.to_owned()))),
+            },
+            None => (spec, true),
+        };
+
+        let levels = |name: &str, default: &str| -> Vec<String> {
+            config
+                .get_string(&format!("dit.{}.levels", name))
+                .unwrap_or_else(|_| String::from(default))
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect()
+        };
+
+        let field = match name {
+            ""          => return Err(Error::from_kind(EK::MalformedSortKey(spec.to_owned()))),
+            "created"   => SortField::Created,
+            "updated"   => SortField::Updated,
+            "replies"   => SortField::Replies,
+            "priority"  => SortField::Metadata(
+                String::from(trailer::PRIORITY_KEY),
+                levels("priority", "low,normal,high,urgent"),
+            ),
+            "severity"  => SortField::Metadata(
+                String::from(trailer::SEVERITY_KEY),
+                levels("severity", "trivial,minor,normal,major,critical"),
+            ),
+            key         => SortField::Metadata(String::from(key), Vec::new()),
+        };
+
+        Ok(SortKey { field: field, descending: descending })
+    }
+
+    /// Check whether evaluating the key requires an issue's metadata
+    ///
+    pub fn needs_metadata(&self) -> bool {
+        match self.field {
+            SortField::Metadata(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Determine the value of an issue to sort by
+    ///
+    /// Times are represented as seconds since the epoch. Values of metadata
+    /// keys found in the levels associated with the key are represented by
+    /// their rank. `None` is returned if the issue doesn't carry a value for
+    /// the key.
+    ///
+    pub fn value(&self, issue: &Issue, metadata: &BTreeMap<String, ValueAccumulator>) -> Result<Option<TrailerValue>> {
+        let value = match self.field {
+            SortField::Created => Some(TrailerValue::Int(issue.initial_message()?.time().seconds())),
+            SortField::Updated => Some(TrailerValue::Int(issue.latest_message()?.time().seconds())),
+            SortField::Replies => {
+                let messages = issue.message_revwalk()?.count() as i64;
+                Some(TrailerValue::Int(messages - 1))
+            },
+            SortField::Metadata(ref key, ref levels) => metadata_value(metadata, key)
+                .map(|value| {
+                    let repr = value.to_string().to_lowercase();
+                    levels
+                        .iter()
+                        .position(|level| level.to_lowercase() == repr)
+                        .map(|rank| TrailerValue::Int(rank as i64))
+                        .unwrap_or_else(|| value.clone())
+                }),
+        };
+        Ok(value)
+    }
+
+    /// Compare the values of two issues
+    ///
+    /// Issues without a value are always placed after issues carrying one,
+    /// regardless of the order.
+    ///
+    pub fn compare(&self, a: &Option<TrailerValue>, b: &Option<TrailerValue>) -> Ordering {
+        match (a, b) {
+            (&Some(ref a), &Some(ref b)) => if self.descending { b.cmp(a) } else { a.cmp(b) },
+            (&Some(_), &None) => Ordering::Less,
+            (&None, &Some(_)) => Ordering::Greater,
+            (&None, &None) => Ordering::Equal,
+        }
+    }
+}
+
+impl Default for SortKey {
+    fn default() -> Self {
+        SortKey { field: SortField::Created, descending: true }
+    }
+}
+
+
+/// Compare the values of two issues according to a sequence of sort keys
+///
+/// Subsequent keys are only considered if the issues compare equal with respect
+/// to the preceding keys.
+///
+pub fn compare(keys: &[SortKey], a: &[Option<TrailerValue>], b: &[Option<TrailerValue>]) -> Ordering {
+    keys.iter()
+        .zip(a.iter().zip(b.iter()))
+        .map(|(key, (a, b))| key.compare(a, b))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
@@ -0,0 +1,352 @@
+//   git-dit - the distributed issue tracker for git
+//   Copyright (C) 2017 Matthias Beyer <mail@beyermatthias.de>
+//   Copyright (C) 2017 Julian Ganz <neither@nut.email>
+//
+//   This program is free software; you can redistribute it and/or modify
+//   it under the terms of the GNU General Public License version 2 as
+//   published by the Free Software Foundation.
+//
+
+//! Summaries of reference updates
+//!
+//! This module provides the `Summary` type, describing updates of references
+//! transferred via fetches or pushes in terms of issues and messages rather than
+//! references. Messages are considered new if they are not reachable from any
+//! reference of the issue known to the receiving side prior to the update.
+//!
+//! The functions `fetch` and `push` perform the respective operation, returning
+//! a summary instead of printing the references updated. Summaries may be
+//! displayed in a human readable form or in a machine readable "porcelain"
+//! format.
+//!
+
+use git2::{self, Oid, Remote, Repository};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+
+use callbacks::{self, Updates};
+use error::*;
+use libgitdit::Issue;
+
+
+/// Update of a reference
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefUpdate {
+    pub name: String,
+    /// Target prior to the update, if the reference existed
+    pub old: Option<Oid>,
+    /// Target after the update, if the reference wasn't deleted
+    pub new: Option<Oid>,
+}
+
+
+/// Get the targets of all dit references in a repository
+///
+pub fn dit_refs(repo: &Repository) -> Result<BTreeMap<String, Oid>> {
+    let mut retval = BTreeMap::new();
+    for reference in repo.references_glob("**/dit/**")? {
+        let reference = reference?;
+        if let (Some(name), Some(target)) = (reference.name(), reference.target()) {
+            retval.insert(name.to_owned(), target);
+        }
+    }
+    Ok(retval)
+}
+
+
+/// Get the name of the remote-tracking reference for a local dit reference
+///
+pub fn tracking_ref(remote: &str, refname: &str) -> String {
+    let refname = without_prefix(refname, "refs/").unwrap_or(refname);
+    format!("refs/remotes/{}/{}", remote, refname)
+}
+
+
+/// Get the remainder of a string if it starts with the given prefix
+///
+fn without_prefix<'a>(string: &'a str, prefix: &str) -> Option<&'a str> {
+    match string.get(..prefix.len()) {
+        Some(head) if head == prefix => string.get(prefix.len()..),
+        _ => None,
+    }
+}
+
+
+/// Get the issue a dit reference is associated with
+///
+/// The issue is determined from the name of the reference, which is expected to
+/// contain the issue's id right after a "dit" component.
+///
+pub fn issue_of_ref(name: &str) -> Option<Oid> {
+    let mut parts = name.split('/').skip_while(|part| *part != "dit");
+    parts.next();
+    parts.next().and_then(|id| Oid::from_str(id).ok())
+}
+
+
+/// Summary of reference updates
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    /// Issues new to the receiving side
+    pub new_issues: BTreeSet<Oid>,
+    /// Number of new replies, by issue
+    pub new_replies: BTreeMap<Oid, usize>,
+    /// Issues of which an existing head was moved
+    pub moved_heads: BTreeSet<Oid>,
+    /// References which were not updated, along with the reason
+    pub rejected: Vec<(String, String)>,
+}
+
+impl Summary {
+    /// Summarize reference updates
+    ///
+    /// The references known to the receiving side prior to the updates have to
+    /// be supplied.
+    ///
+    pub fn of(repo: &Repository, updates: &[RefUpdate], known: &BTreeMap<String, Oid>) -> Result<Self> {
+        let mut retval = Summary::default();
+
+        let mut tips : BTreeMap<Oid, Vec<Oid>> = BTreeMap::new();
+        for update in updates.iter().filter(|update| update.old != update.new) {
+            let issue = match issue_of_ref(&update.name) {
+                Some(issue) => issue,
+                None => continue,
+            };
+            tips.entry(issue).or_insert_with(Vec::new).extend(update.new);
+            if update.old.is_some() && update.new.is_some() && update.name.ends_with("/head") {
+                retval.moved_heads.insert(issue);
+            }
+        }
+
+        for (issue, tips) in tips {
+            // Patch sets and messages referring to other commits must not be
+            // reported as replies, hence we only walk the issue's messages.
+            let mut revwalk = Issue::new(repo, issue).message_revwalk_from(tips)?;
+            for (_, target) in known.iter().filter(|known| issue_of_ref(known.0) == Some(issue)) {
+                revwalk.hide(*target)?;
+            }
+
+            let mut replies = 0;
+            for message in revwalk {
+                if message? == issue {
+                    retval.new_issues.insert(issue);
+                } else {
+                    replies += 1;
+                }
+            }
+            if replies > 0 {
+                retval.new_replies.insert(issue, replies);
+            }
+        }
+
+        // heads of new issues are not considered moved
+        for issue in retval.new_issues.iter() {
+            retval.moved_heads.remove(issue);
+        }
+
+        Ok(retval)
+    }
+
+    /// Describe the updates in a human readable way
+    ///
+    /// Ids are abbreviated to the length supplied.
+    ///
+    pub fn describe(&self, id_len: usize) -> String {
+        let mut parts = Vec::new();
+        if !self.new_issues.is_empty() {
+            parts.push(plural(self.new_issues.len(), "new issue", "new issues"));
+        }
+        if !self.new_replies.is_empty() {
+            let replies = self.new_replies.values().sum();
+            parts.push(format!("{} in {}",
+                               plural(replies, "new reply", "new replies"),
+                               plural(self.new_replies.len(), "issue", "issues")));
+        }
+        for issue in self.moved_heads.iter() {
+            parts.push(format!("head of {0:.1$} moved", issue, id_len));
+        }
+        for &(ref name, ref reason) in self.rejected.iter() {
+            parts.push(format!("{} rejected ({})", name, reason));
+        }
+
+        if parts.is_empty() {
+            String::from("up to date")
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    /// Describe the updates in a machine readable way
+    ///
+    /// Each line consists of whitespace separated fields: the direction
+    /// ("fetch" or "push"), the remote, the kind of update and the issue or,
+    /// for rejected references, the reference. The number of replies follows
+    /// "new-replies" entries and the reason follows "rejected" entries.
+    ///
+    pub fn porcelain(&self, direction: &str, remote: &str) -> Vec<String> {
+        let mut retval = Vec::new();
+        for issue in self.new_issues.iter() {
+            retval.push(format!("{} {} new-issue {}", direction, remote, issue));
+        }
+        for (issue, replies) in self.new_replies.iter() {
+            retval.push(format!("{} {} new-replies {} {}", direction, remote, issue, replies));
+        }
+        for issue in self.moved_heads.iter() {
+            retval.push(format!("{} {} head-moved {}", direction, remote, issue));
+        }
+        for &(ref name, ref reason) in self.rejected.iter() {
+            retval.push(format!("{} {} rejected {} {}", direction, remote, name, reason));
+        }
+        retval
+    }
+}
+
+
+/// Fetch from a remote
+///
+/// The summary of the references updated is returned.
+///
+pub fn fetch(repo: &Repository, remote: &mut Remote, refspecs: &[&str], prune: bool) -> Result<Summary> {
+    let known = dit_refs(repo)?;
+
+    let updates = RefCell::new(Updates::default());
+    {
+        let mut fetch_options = git2::FetchOptions::new();
+        fetch_options.prune(if prune {
+            git2::FetchPrune::On
+        } else {
+            git2::FetchPrune::Unspecified
+        });
+        fetch_options.remote_callbacks(callbacks::collecting_callbacks(&updates));
+        remote.fetch(refspecs, Some(&mut fetch_options), None)?;
+    }
+
+    Summary::of(repo, &updates.into_inner().tips, &known)
+}
+
+
+/// Determine the updates required for pushing local references to a remote
+///
+/// The old target of each update is the one of the remote-tracking reference,
+/// e.g. the target on the remote as of the last fetch. References which are
+/// already present on the remote or which the remote is ahead of are omitted.
+///
+pub fn push_updates<I>(repo: &Repository, remote: &str, refnames: I) -> Result<Vec<RefUpdate>>
+    where I: IntoIterator<Item = String>
+{
+    let mut retval = Vec::new();
+    for refname in refnames {
+        let new = repo.refname_to_id(&refname)?;
+        let old = repo.refname_to_id(&tracking_ref(remote, &refname)).ok();
+        if let Some(old) = old {
+            if old == new || repo.graph_descendant_of(old, new)? {
+                continue;
+            }
+        }
+        retval.push(RefUpdate { name: refname, old: old, new: Some(new) });
+    }
+    Ok(retval)
+}
+
+
+/// Check whether an update is a fast-forward
+///
+pub fn is_fast_forward(repo: &Repository, update: &RefUpdate) -> Result<bool> {
+    match (update.old, update.new) {
+        (Some(old), Some(new)) => Ok(old == new || repo.graph_descendant_of(new, old)?),
+        _ => Ok(true),
+    }
+}
+
+
+/// Get the dit references currently present on a remote
+///
+pub fn remote_refs(remote: &mut Remote) -> Result<BTreeMap<String, Oid>> {
+    let connection = remote.connect_auth(git2::Direction::Push, Some(callbacks::callbacks()), None)?;
+    Ok(connection
+        .list()?
+        .iter()
+        .filter(|head| head.name().starts_with("refs/dit/"))
+        .map(|head| (head.name().to_owned(), head.oid()))
+        .collect())
+}
+
+
+/// Push to a remote
+///
+/// The local references to push are supplied as updates, with the old target
+/// being the one of the remote-tracking reference. Updates which are not
+/// fast-forwards are reported as rejected unless forced. Forced updates are
+/// still rejected if the reference on the remote doesn't have the old target,
+/// e.g. if it changed since the last fetch. The remote-tracking references of
+/// all references accepted by the remote are updated. The summary of the
+/// references updated on the remote is returned.
+///
+/// Note that the targets on the remote are checked via a connection of their
+/// own, since pushing always establishes a new connection. References updated
+/// on the remote between the check and the push may thus be overwritten.
+///
+pub fn push(repo: &Repository, remote: &mut Remote, updates: Vec<RefUpdate>, force: bool) -> Result<Summary> {
+    let name = remote.name().unwrap_or_default().to_owned();
+    let prefix = tracking_ref(&name, "refs/dit/");
+    let known : BTreeMap<String, Oid> = dit_refs(repo)?
+        .into_iter()
+        .filter(|&(ref refname, _)| refname.starts_with(&prefix))
+        .collect();
+
+    let mut forced = Vec::new();
+    let mut rejected = Vec::new();
+    let mut refspecs = Vec::new();
+    for update in updates.iter() {
+        match (is_fast_forward(repo, update)?, force) {
+            (true, _)       => refspecs.push(update.name.clone()),
+            (false, true)   => forced.push(update),
+            (false, false)  => rejected.push((update.name.clone(), String::from("non-fast-forward"))),
+        }
+    }
+
+    // We check the targets on the remote as late as possible in order to keep
+    // the window for concurrent updates small.
+    if !forced.is_empty() {
+        let current = remote_refs(remote)?;
+        for update in forced {
+            if current.get(&update.name).cloned() == update.old {
+                refspecs.push(format!("+{}", update.name));
+            } else {
+                rejected.push((update.name.clone(), String::from("stale")));
+            }
+        }
+    }
+
+    let collected = RefCell::new(Updates::default());
+    if !refspecs.is_empty() {
+        let mut push_options = git2::PushOptions::new();
+        push_options.remote_callbacks(callbacks::collecting_callbacks(&collected));
+        let refspecs : Vec<&str> = refspecs.iter().map(String::as_str).collect();
+        remote.push(refspecs.as_ref(), Some(&mut push_options))?;
+    }
+    rejected.extend(collected.into_inner().rejected);
+
+    let pushed : Vec<RefUpdate> = updates
+        .into_iter()
+        .filter(|update| !rejected.iter().any(|r| r.0 == update.name))
+        .collect();
+    for update in pushed.iter() {
+        if let Some(target) = update.new {
+            repo.reference(&tracking_ref(&name, &update.name), target, true, "dit: push")?;
+        }
+    }
+
+    let mut retval = Summary::of(repo, &pushed, &known)?;
+    retval.rejected = rejected;
+    Ok(retval)
+}
+
+
+/// Format a count along with the singular or plural form of a noun
+///
+fn plural(count: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
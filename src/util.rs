@@ -9,27 +9,104 @@
 
 use clap::{ArgMatches, Values};
 use git2::{self, Commit, Repository};
-use std::fs::File;
-use std::io;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use abort::IteratorExt;
+use callbacks;
 use error::ErrorKind as EK;
 use error::*;
-use programs::run_editor;
-use libgitdit::{Issue, RepositoryExt};
+use programs::{run_editor, verify_commit};
+use libgitdit::{Issue, Message, RemoteExt, RepositoryExt};
+use libgitdit::index::Index;
+use libgitdit::issue::{MetadataWalk, Trust};
 use libgitdit::message::LineIteratorExt;
-use libgitdit::message::trailer::Trailer;
+use libgitdit::message::accumulation::ValueAccumulator;
+use libgitdit::message::schema::Schema;
+use libgitdit::message::trailer::{self, PairsToTrailers, Trailer, TrailerValue};
 
 /// Open the DIT repo
 ///
 /// Opens the DIT repo corresponding to the current one honouring the user
 /// configuration.
 ///
+/// Issues may be kept in a dedicated bug repository, configured via
+/// `dit.repository`. The value is either the path of a repository, relative to
+/// the current one's working directory, or the URL of a remote repository. In
+/// the latter case, a local clone holding the issues is maintained in the
+/// current repository's `dit/repository` directory. The clone is created on
+/// first use, fetching all issues from the remote, which is named "origin". If
+/// the fetch fails, no clone is left behind.
+///
 pub fn open_dit_repo() -> Result<Repository> {
-    // TODO: access the config and maybe return another repo instead
-    Repository::open_from_env().chain_err(|| EK::CannotOpenRepository)
+    let repo = Repository::open_from_env().chain_err(|| EK::CannotOpenRepository)?;
+
+    let location = match repo.config().and_then(|config| config.get_string("dit.repository")) {
+        Ok(location) => location,
+        Err(_) => return Ok(repo),
+    };
+
+    if !is_url(&location) {
+        let path = repo.workdir().unwrap_or(repo.path()).join(&location);
+        return Repository::open(path).chain_err(|| EK::CannotOpenBugRepository(location));
+    }
+
+    let path = repo.state_dir()?.join("repository");
+    if path.exists() {
+        return Repository::open(path).chain_err(|| EK::CannotOpenBugRepository(location));
+    }
+
+    // The clone is created in a temporary location and only moved into place
+    // once the issues were fetched. Otherwise, a failed fetch would leave an
+    // empty repository behind, which would be used silently from then on.
+    let tmp_path = path.with_extension("tmp");
+    if tmp_path.exists() {
+        fs::remove_dir_all(&tmp_path).chain_err(|| EK::WrappedIOError)?;
+    }
+    if let Err(err) = clone_issues(&tmp_path, &location) {
+        let _ = fs::remove_dir_all(&tmp_path);
+        return Err(err);
+    }
+    fs::rename(&tmp_path, &path).chain_err(|| EK::WrappedIOError)?;
+    Repository::open(path).chain_err(|| EK::CannotOpenBugRepository(location))
+}
+
+
+/// Create a bare repository holding the issues of a remote repository
+///
+/// The remote is added as "origin" and all issues are fetched from it.
+///
+fn clone_issues(path: &Path, location: &str) -> Result<()> {
+    let clone = Repository::init_bare(path).chain_err(|| EK::CannotOpenBugRepository(location.to_owned()))?;
+    let mut remote = clone
+        .remote("origin", location)
+        .chain_err(|| EK::CannotOpenBugRepository(location.to_owned()))?;
+    let refspec = remote.all_issues_refspec().unwrap();
+    let mut fetch_options = git2::FetchOptions::new();
+    fetch_options.remote_callbacks(callbacks::callbacks());
+    remote
+        .fetch(&[refspec.as_str()], Some(&mut fetch_options), None)
+        .chain_err(|| EK::CannotOpenBugRepository(location.to_owned()))
+}
+
+
+/// Check whether the location of a repository is a URL
+///
+/// Both URLs including a scheme and scp-like locations of the form
+/// `[user@]host:path` are considered URLs.
+///
+fn is_url(location: &str) -> bool {
+    if location.contains("://") {
+        return true;
+    }
+    match (location.find(':'), location.find('/')) {
+        // single letters are considered drive letters rather than hosts
+        (Some(colon), slash) => colon > 1 && slash.map(|slash| colon < slash).unwrap_or(true),
+        _ => false,
+    }
 }
 
 
@@ -48,6 +125,15 @@ pub trait RepositoryUtil<'r> {
     ///
     fn value_to_issue(&'r self, value: &str) -> Result<Issue<'r>>;
 
+    /// Get the commits delimiting a range
+    ///
+    /// This function returns the base and the tip of a range of the form
+    /// `<base>..<tip>`. If only a single rev is supplied, it is interpreted as
+    /// the base and `HEAD` is used as the tip. For ranges of the form
+    /// `<a>...<b>`, the merge base of both revs is used as the base.
+    ///
+    fn value_to_range(&'r self, range: &str) -> Result<(Commit<'r>, Commit<'r>)>;
+
     /// Get a vector of commits from values
     ///
     /// This function transforms values to a vector.
@@ -81,11 +167,138 @@ pub trait RepositoryUtil<'r> {
 
     /// Retrieve metadata from command line arguments
     ///
+    /// The metadata is validated against the metadata schema.
+    ///
     fn prepare_trailers(&self, matches: &ArgMatches) -> Result<Vec<Trailer>>;
 
+    /// Get the metadata schema
+    ///
+    fn metadata_schema(&self) -> Result<Schema>;
+
+    /// Get the selection of messages from which metadata is accumulated
+    ///
+    fn metadata_walk(&self) -> Result<MetadataWalk>;
+
+    /// Get the messages of an issue from which metadata is accumulated
+    ///
+    /// In addition to the selection performed by the library, signatures are
+    /// verified if only signed messages are trusted.
+    ///
+    fn metadata_messages(&'r self, issue: &Issue<'r>, walk: &MetadataWalk) -> Result<Vec<Commit<'r>>>;
+
+    /// Get the metadata of an issue
+    ///
+    /// The metadata is accumulated from the messages selected as configured,
+    /// according to the metadata schema.
+    ///
+    fn issue_metadata(&'r self, issue: &Issue<'r>) -> Result<BTreeMap<String, ValueAccumulator>>;
+
+    /// Validate the trailers in a message against the metadata schema
+    ///
+    fn validate_trailers<I, S>(&self, lines: I) -> Result<()>
+        where I: IntoIterator<Item = S>,
+              S: AsRef<str>;
+
+    /// Get an issue template
+    ///
+    /// Templates are looked up via the path configured as `dit.template.<name>`
+    /// or as `templates/<name>` in the repository-level configuration, in that
+    /// order. The template is returned as a sequence of lines.
+    ///
+    fn template(&self, name: &str) -> Result<Vec<String>>;
+
     /// Get the abbreviation length for oids
     ///
     fn abbreviation_length(&self, matches: &ArgMatches) -> Result<usize>;
+
+    /// Resolve a person
+    ///
+    /// The person may be supplied in the form `Name <email>`, as an email
+    /// address or as a name known to the mailmap. If no person is supplied,
+    /// the user's identity is used. The canonical identity according to the
+    /// mailmap is returned.
+    ///
+    fn resolve_person(&self, person: Option<&str>) -> Result<TrailerValue>;
+
+    /// Get the status values considered closed
+    ///
+    /// The values are configured as a comma separated list via
+    /// `dit.status.closed` and default to "closed".
+    ///
+    fn closed_states(&self) -> Result<Vec<String>>;
+
+    /// Get the directory holding git-dit's local state
+    ///
+    /// The directory `dit` within the repository's git directory is created
+    /// if it doesn't exist yet.
+    ///
+    fn state_dir(&self) -> Result<PathBuf>;
+
+    /// Get the targets of the dit references as of the user's last `whatsnew`
+    /// query
+    ///
+    /// The bookmarks of all users are stored in the file `whatsnew` in the
+    /// state directory, keyed by their email address.
+    ///
+    fn whatsnew_bookmark(&self) -> Result<Option<Vec<git2::Oid>>>;
+
+    /// Set the targets of the dit references as of the user's last `whatsnew`
+    /// query
+    ///
+    fn set_whatsnew_bookmark(&self, tips: Vec<git2::Oid>) -> Result<()>;
+
+    /// Get the repositories whose issues are aggregated
+    ///
+    /// Additional repositories are configured via `dit.aggregate.<name>.path`.
+    /// Relative paths are interpreted relative to the repository's working
+    /// directory. The repositories are returned along with their names,
+    /// ordered by name. Repositories which cannot be opened are skipped with a
+    /// warning.
+    ///
+    fn aggregated_repositories(&self) -> Result<Vec<(String, Repository)>>;
+
+    /// Get an aggregated repository by name
+    ///
+    fn aggregated_repository(&self, name: &str) -> Result<Repository>;
+
+    /// Get the full-text index of the repository
+    ///
+    /// The index is stored in the file `index` in the state directory. It is
+    /// brought up to date before being returned.
+    ///
+    fn full_text_index(&self) -> Result<Index>;
+
+    /// Get the messages the user has seen
+    ///
+    /// The ids of the messages displayed to the user are stored in the file
+    /// `seen` in the state directory, one per line.
+    ///
+    fn seen_messages(&self) -> Result<BTreeSet<git2::Oid>>;
+
+    /// Mark messages as seen
+    ///
+    /// The ids supplied are appended to the ones already stored.
+    ///
+    fn mark_seen<I>(&self, messages: I) -> Result<()>
+        where I: IntoIterator<Item = git2::Oid>;
+
+    /// Set a single-valued piece of metadata of an issue
+    ///
+    /// The trailer is validated against the metadata schema and recorded via
+    /// `tag_issue`, unless the issue already carries the value. An empty value
+    /// removes the metadata. Returns the id of the new message, if one was
+    /// created.
+    ///
+    fn set_metadata(&'r self, issue: &Issue<'r>, trailer: Trailer) -> Result<Option<git2::Oid>>;
+
+    /// Add a message carrying metadata on top of an issue's head
+    ///
+    /// The message will carry the trailers supplied and refer to the commits
+    /// supplied. The local head reference of the issue is updated to the new
+    /// message, whose id is returned. The message is placed on top of the head
+    /// returned by `Issue::head()`.
+    ///
+    fn tag_issue(&'r self, issue: &Issue<'r>, trailers: &[Trailer], references: &[Commit<'r>]) -> Result<git2::Oid>;
 }
 
 impl<'r> RepositoryUtil<'r> for Repository {
@@ -103,6 +316,33 @@ impl<'r> RepositoryUtil<'r> for Repository {
             })
     }
 
+    fn value_to_range(&'r self, range: &str) -> Result<(Commit<'r>, Commit<'r>)> {
+        let revspec = self.revparse(range).chain_err(|| EK::WrappedGitDitError)?;
+        let to_commit = |object: &git2::Object<'r>| {
+            object.peel(git2::ObjectType::Commit)
+                  .map(|object| object.into_commit().ok().unwrap())
+                  .chain_err(|| EK::WrappedGitDitError)
+        };
+
+        let base = match revspec.from() {
+            Some(object) => to_commit(object)?,
+            None => return Err(Error::from_kind(EK::MalformedRange(range.to_owned()))),
+        };
+        let tip = match revspec.to() {
+            Some(object) => to_commit(object)?,
+            None => self.value_to_commit("HEAD")?,
+        };
+
+        if revspec.mode().contains(git2::REVPARSE_MERGE_BASE) {
+            let merge_base = self
+                .merge_base(base.id(), tip.id())
+                .chain_err(|| EK::MalformedRange(range.to_owned()))?;
+            let base = self.find_commit(merge_base).chain_err(|| EK::WrappedGitDitError)?;
+            return Ok((base, tip));
+        }
+        Ok((base, tip))
+    }
+
     fn values_to_hashes(&'r self, values: Values) -> Result<Vec<Commit<'r>>> {
         let mut retval = Vec::new();
         for commit in values.map(|string| self.value_to_commit(string)) {
@@ -151,6 +391,7 @@ impl<'r> RepositoryUtil<'r> for Repository {
         lines.iter()
             .check_message_format()
             .chain_err(|| EK::WrappedGitDitError)?;
+        self.validate_trailers(lines.iter())?;
 
         Ok(lines)
     }
@@ -165,14 +406,77 @@ impl<'r> RepositoryUtil<'r> for Repository {
 
         // append misc metadata
         if let Some(metadata) = matches.values_of("metadata") {
+            let schema = self.metadata_schema()?;
             for trailer in metadata.map(Trailer::from_str) {
-                trailers.push(trailer?);
+                let trailer = trailer?;
+                schema.validate(&trailer).chain_err(|| EK::WrappedGitDitError)?;
+                trailers.push(trailer);
             }
         }
 
         Ok(trailers)
     }
 
+    fn metadata_schema(&self) -> Result<Schema> {
+        self.config()
+            .chain_err(|| EK::CannotGetRepositoryConfig)
+            .and_then(|config| Schema::from_config(&config).chain_err(|| EK::WrappedGitDitError))
+    }
+
+    fn metadata_walk(&self) -> Result<MetadataWalk> {
+        self.config()
+            .chain_err(|| EK::CannotGetRepositoryConfig)
+            .and_then(|config| MetadataWalk::from_config(&config).chain_err(|| EK::WrappedGitDitError))
+    }
+
+    fn metadata_messages(&'r self, issue: &Issue<'r>, walk: &MetadataWalk) -> Result<Vec<Commit<'r>>> {
+        let messages = issue.metadata_messages(walk).chain_err(|| EK::WrappedGitDitError)?;
+        if walk.trust == Trust::Signed {
+            verify_signatures(self, messages)
+        } else {
+            Ok(messages)
+        }
+    }
+
+    fn issue_metadata(&'r self, issue: &Issue<'r>) -> Result<BTreeMap<String, ValueAccumulator>> {
+        let schema = self.metadata_schema()?;
+        let walk = self.metadata_walk()?;
+        let messages = self.metadata_messages(issue, &walk)?;
+        Ok(schema.accumulate(messages.into_iter().flat_map(|message| message.trailers())))
+    }
+
+    fn validate_trailers<I, S>(&self, lines: I) -> Result<()>
+        where I: IntoIterator<Item = S>,
+              S: AsRef<str>
+    {
+        let schema = self.metadata_schema()?;
+        for trailer in lines.into_iter().trailers() {
+            schema.validate(&trailer).chain_err(|| EK::WrappedGitDitError)?;
+        }
+        Ok(())
+    }
+
+    fn template(&self, name: &str) -> Result<Vec<String>> {
+        let config = self.config().chain_err(|| EK::CannotGetRepositoryConfig)?;
+
+        // a template configured locally takes precedence over templates in the
+        // repository-level configuration
+        let content = if let Ok(path) = config.get_path(&format!("dit.template.{}", name)) {
+            let path = self.workdir().unwrap_or(self.path()).join(path);
+            let mut content = String::new();
+            File::open(path)
+                .and_then(|mut file| file.read_to_string(&mut content))
+                .chain_err(|| EK::WrappedIOError)?;
+            content
+        } else {
+            self.dit_config_file(&format!("templates/{}", name))
+                .chain_err(|| EK::WrappedGitDitError)?
+                .ok_or_else(|| Error::from_kind(EK::UnknownTemplate(name.to_owned())))?
+        };
+
+        Ok(content.lines().map(String::from).collect())
+    }
+
     fn abbreviation_length(&self, matches: &ArgMatches) -> Result<usize> {
         if !matches.is_present("abbrev") {
             // If the abbreviation option was not used, we can just use the
@@ -197,6 +501,167 @@ impl<'r> RepositoryUtil<'r> for Repository {
         // TODO: use a larger number based on the number of objects in the repo
         Ok(7)
     }
+
+    fn resolve_person(&self, person: Option<&str>) -> Result<TrailerValue> {
+        let mailmap = self.mailmap().chain_err(|| EK::WrappedGitDitError)?;
+        let (name, email) = match person {
+            Some(person) => match TrailerValue::person_from_slice(person) {
+                Some(TrailerValue::Person { name, email }) => mailmap.canonical(&name, &email),
+                _ => mailmap
+                    .lookup(person)
+                    .ok_or_else(|| Error::from_kind(EK::UnknownPerson(person.to_owned())))?,
+            },
+            None => {
+                let sig = self.signature().chain_err(|| EK::CannotGetSignature)?;
+                mailmap.canonical(sig.name().unwrap_or(""), sig.email().unwrap_or(""))
+            },
+        };
+        Ok(TrailerValue::Person { name: name, email: email })
+    }
+
+    fn closed_states(&self) -> Result<Vec<String>> {
+        let states = self
+            .config()
+            .chain_err(|| EK::CannotGetRepositoryConfig)?
+            .get_string("dit.status.closed")
+            .unwrap_or_else(|_| String::from("closed"));
+        Ok(states.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect())
+    }
+
+    fn state_dir(&self) -> Result<PathBuf> {
+        let dir = self.path().join("dit");
+        fs::create_dir_all(&dir).chain_err(|| EK::WrappedIOError)?;
+        Ok(dir)
+    }
+
+    fn whatsnew_bookmark(&self) -> Result<Option<Vec<git2::Oid>>> {
+        let user = user_email(self)?;
+        Ok(read_bookmarks(&self.state_dir()?.join("whatsnew"))?.remove(&user))
+    }
+
+    fn set_whatsnew_bookmark(&self, tips: Vec<git2::Oid>) -> Result<()> {
+        let path = self.state_dir()?.join("whatsnew");
+        let mut bookmarks = read_bookmarks(&path)?;
+        bookmarks.insert(user_email(self)?, tips);
+
+        let mut file = File::create(path).chain_err(|| EK::WrappedIOError)?;
+        for (user, tips) in bookmarks {
+            for tip in tips {
+                writeln!(file, "{} {}", user, tip).chain_err(|| EK::WrappedIOError)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn aggregated_repositories(&self) -> Result<Vec<(String, Repository)>> {
+        let config = self.config().chain_err(|| EK::CannotGetRepositoryConfig)?;
+        let mut names = Vec::new();
+        for entry in &config.entries(Some(r"dit\.aggregate\..*\.path$"))? {
+            let entry = entry?;
+            let name = entry
+                .name()
+                .map(|name| name["dit.aggregate.".len()..name.len() - ".path".len()].to_owned());
+            names.extend(name);
+        }
+        names.sort();
+        names.dedup();
+
+        // a repository which vanished shouldn't prevent access to the others
+        Ok(names
+            .into_iter()
+            .filter_map(|name| match self.aggregated_repository(&name) {
+                Ok(repo) => Some((name, repo)),
+                Err(err) => {
+                    warn!("Skipping aggregated repository {}: {}", name, err);
+                    None
+                },
+            })
+            .collect())
+    }
+
+    fn aggregated_repository(&self, name: &str) -> Result<Repository> {
+        let path = self
+            .config()
+            .chain_err(|| EK::CannotGetRepositoryConfig)?
+            .get_path(&format!("dit.aggregate.{}.path", name))
+            .chain_err(|| EK::UnknownRepository(name.to_owned()))?;
+        Repository::open(self.workdir().unwrap_or(self.path()).join(path))
+            .chain_err(|| EK::CannotOpenRepository)
+    }
+
+    fn full_text_index(&self) -> Result<Index> {
+        let path = self.state_dir()?.join("index");
+        let mut index = Index::load(&path).chain_err(|| EK::WrappedGitDitError)?;
+        let tips = index.tips().clone();
+        // the tips may change without new messages, e.g. if a head is moved
+        if index.update(self).chain_err(|| EK::WrappedGitDitError)? > 0 || *index.tips() != tips {
+            index.save(&path).chain_err(|| EK::WrappedGitDitError)?;
+        }
+        Ok(index)
+    }
+
+    fn seen_messages(&self) -> Result<BTreeSet<git2::Oid>> {
+        let mut content = String::new();
+        match File::open(self.state_dir()?.join("seen")) {
+            Ok(mut file) => file.read_to_string(&mut content).chain_err(|| EK::WrappedIOError)?,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeSet::new()),
+            Err(err) => return Err(err).chain_err(|| EK::WrappedIOError),
+        };
+
+        Ok(content.lines().filter_map(|line| git2::Oid::from_str(line.trim()).ok()).collect())
+    }
+
+    fn mark_seen<I>(&self, messages: I) -> Result<()>
+        where I: IntoIterator<Item = git2::Oid>
+    {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.state_dir()?.join("seen"))
+            .chain_err(|| EK::WrappedIOError)?;
+        for id in messages {
+            writeln!(file, "{}", id).chain_err(|| EK::WrappedIOError)?;
+        }
+        Ok(())
+    }
+
+    fn set_metadata(&'r self, issue: &Issue<'r>, trailer: Trailer) -> Result<Option<git2::Oid>> {
+        self.metadata_schema()?.validate(&trailer).chain_err(|| EK::WrappedGitDitError)?;
+
+        let metadata = self.issue_metadata(issue)?;
+        let current = metadata_value(&metadata, trailer.key.as_ref())
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+        if current == trailer.value.to_string() {
+            return Ok(None);
+        }
+
+        self.tag_issue(issue, &[trailer], &[]).map(Some)
+    }
+
+    fn tag_issue(&'r self, issue: &Issue<'r>, trailers: &[Trailer], references: &[Commit<'r>]) -> Result<git2::Oid> {
+        let mut head = issue
+            .head()
+            .chain_err(|| EK::WrappedGitDitError)?
+            .peel(git2::ObjectType::Commit)?
+            .into_commit()
+            .map_err(|_| Error::from_kind(EK::WrappedGitDitError))?;
+
+        // construct the message
+        let sig = self.signature().chain_err(|| EK::CannotGetSignature)?;
+        let message = [head.reply_subject().unwrap_or_default(), String::new()]
+            .to_vec()
+            .into_iter()
+            .chain(trailers.iter().map(|t| t.to_string()))
+            .collect_string();
+        let tree = self.empty_tree().chain_err(|| EK::WrappedGitDitError)?;
+        let parent_refs : Vec<&Commit> = Some(&head).into_iter().chain(references.iter()).collect();
+        let new = self.commit(None, &sig, &sig, message.trim(), &tree, &parent_refs)?;
+
+        // update the head reference
+        issue.update_head(new).chain_err(|| EK::WrappedGitDitError)?;
+        Ok(new)
+    }
 }
 
 /// Get the message specified on the command line, as lines
@@ -211,3 +676,153 @@ pub fn message_from_args(matches: &ArgMatches) -> Option<Vec<String>> {
                        .collect())
 }
 
+
+/// Retain only the messages carrying a good signature
+///
+pub fn verify_signatures<'r>(repo: &Repository, messages: Vec<Commit<'r>>) -> Result<Vec<Commit<'r>>> {
+    let mut retval = Vec::new();
+    for message in messages {
+        if verify_commit(repo.path(), message.id())? {
+            retval.push(message);
+        }
+    }
+    Ok(retval)
+}
+
+
+/// Convert accumulated metadata into trailers
+///
+/// The trailers are ordered by key.
+///
+pub fn metadata_trailers(metadata: BTreeMap<String, ValueAccumulator>) -> Vec<Trailer> {
+    let pairs = metadata
+        .into_iter()
+        .flat_map(|(key, acc)| acc.into_iter().map(move |value| (key.clone(), value)));
+    PairsToTrailers::from(pairs).collect()
+}
+
+
+/// Extract the values accumulated for a key from accumulated metadata
+///
+pub fn metadata_values(metadata: &mut BTreeMap<String, ValueAccumulator>, key: &str) -> Vec<TrailerValue> {
+    metadata
+        .remove(key)
+        .map(|acc| acc.into_iter().collect())
+        .unwrap_or_default()
+}
+
+
+/// Check whether an issue is open, based on its accumulated metadata
+///
+/// An issue is considered open unless its latest status is one of the closed
+/// states supplied.
+///
+pub fn is_open(metadata: &BTreeMap<String, ValueAccumulator>, closed: &[String]) -> bool {
+    metadata_value(metadata, trailer::STATUS_KEY)
+        .map(|status| !closed.contains(&status.to_string()))
+        .unwrap_or(true)
+}
+
+
+/// Get the value accumulated for a key from accumulated metadata
+///
+/// Returns the first value accumulated, unless it is empty. For keys
+/// accumulated as a list, this is the latest value.
+///
+pub fn metadata_value<'a>(metadata: &'a BTreeMap<String, ValueAccumulator>, key: &str) -> Option<&'a TrailerValue> {
+    metadata
+        .get(key)
+        .and_then(|acc| acc.iter().next())
+        .and_then(|value| if value.to_string().is_empty() { None } else { Some(value) })
+}
+
+
+/// Split an issue id qualified by the name of an aggregated repository
+///
+/// Issue ids may be qualified in the form `<name>:<id>`. This function returns
+/// the name, if present, and the plain id.
+///
+pub fn qualified_issue(value: &str) -> (Option<&str>, &str) {
+    match value.find(':') {
+        Some(pos) => (Some(&value[..pos]), &value[pos + 1..]),
+        None => (None, value),
+    }
+}
+
+
+/// Get the issue an issue is a duplicate of
+///
+/// The original issue is extracted from the issue's accumulated metadata.
+///
+pub fn duplicate_of(metadata: &BTreeMap<String, ValueAccumulator>) -> Option<git2::Oid> {
+    match metadata_value(metadata, trailer::DUPLICATE_KEY) {
+        Some(&TrailerValue::Oid(id)) => Some(id),
+        _ => None,
+    }
+}
+
+
+/// Check whether any message of an issue carries a specific trailer
+///
+/// All messages of the issue are scanned, regardless of the metadata walk. This
+/// is considerably cheaper than accumulating the metadata of the issue and may
+/// be used for ruling out issues before doing so.
+///
+pub fn carries_trailer(issue: &Issue, repo: &Repository, key: &str, value: &str) -> Result<bool> {
+    for id in issue.message_revwalk().chain_err(|| EK::WrappedGitDitError)? {
+        let message = id
+            .and_then(|id| repo.find_commit(id))
+            .chain_err(|| EK::WrappedGitDitError)?;
+        if message.trailers().any(|t| t.key.as_ref() == key && t.value.to_string() == value) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+
+/// Count the messages of an issue the user didn't see yet
+///
+pub fn unread_count(issue: &Issue, seen: &BTreeSet<git2::Oid>) -> Result<usize> {
+    let mut count = 0;
+    for id in issue.message_revwalk().chain_err(|| EK::WrappedGitDitError)? {
+        if !seen.contains(&id?) {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+
+/// Get the email address of the user
+///
+fn user_email(repo: &Repository) -> Result<String> {
+    let signature = repo.signature().chain_err(|| EK::CannotGetSignature)?;
+    Ok(signature.email().unwrap_or("").to_owned())
+}
+
+
+/// Read a file holding per-user bookmarks
+///
+/// Each line of the file holds a user's email address followed by the id of a
+/// commit. A user may have multiple lines. A missing file is treated like an
+/// empty one.
+///
+fn read_bookmarks(path: &Path) -> Result<BTreeMap<String, Vec<git2::Oid>>> {
+    let mut content = String::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_string(&mut content).chain_err(|| EK::WrappedIOError)?,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(err) => return Err(err).chain_err(|| EK::WrappedIOError),
+    };
+
+    let mut retval : BTreeMap<String, Vec<git2::Oid>> = BTreeMap::new();
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next().and_then(|id| git2::Oid::from_str(id).ok())) {
+            (Some(user), Some(id)) => retval.entry(user.to_owned()).or_insert_with(Vec::new).push(id),
+            _ => continue,
+        }
+    }
+    Ok(retval)
+}